@@ -0,0 +1,271 @@
+use crate::permit::{try_bech32_to_canonical, AuthContext, Permit};
+use crate::viewing_keys::ViewingKey;
+use cosmwasm_std::{
+    from_slice, to_binary, Api, Binary, CanonicalAddr, HumanAddr, StdError, StdResult,
+};
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+/// Either of the supported ways to authenticate a query
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+#[allow(clippy::large_enum_variant)]
+pub enum Auth<T: Serialize> {
+    Permit(Permit<T>),
+    ViewingKey { address: HumanAddr, key: String },
+}
+
+impl<T: Serialize> Auth<T> {
+    /// Returns the authenticated address, stored_hash loads the viewing key hash saved for an address
+    pub fn authenticate<A, K, F, const KEY_SIZE: usize>(
+        &self,
+        api: &A,
+        msg_type: Option<String>,
+        stored_hash: F,
+    ) -> StdResult<CanonicalAddr>
+    where
+        A: Api,
+        K: ViewingKey<KEY_SIZE> + From<String>,
+        F: Fn(&CanonicalAddr) -> StdResult<Option<Vec<u8>>>,
+    {
+        match self {
+            Auth::Permit(permit) => permit.validate_canonical(api, msg_type),
+            Auth::ViewingKey { address, key } => {
+                let canonical = try_bech32_to_canonical(address.as_str())?;
+
+                // Same error for both cases so the key status isnt leaked
+                match stored_hash(&canonical)? {
+                    Some(hash) if K::from(key.clone()).compare(&hash) => Ok(canonical),
+                    _ => Err(StdError::generic_err(
+                        "Wrong viewing key for this address or viewing key not set",
+                    )),
+                }
+            }
+        }
+    }
+}
+
+/// Query forwarded to another contract along with the identity this contract validated,
+/// the receiver should only trust it when it comes from a contract it knows
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct ForwardedQuery<Q> {
+    pub chain_id: String,
+    pub query: Q,
+    pub signer: HumanAddr,
+}
+
+impl<Q: Serialize> ForwardedQuery<Q> {
+    pub fn new(context: &AuthContext, query: Q) -> Self {
+        Self {
+            chain_id: context.chain_id.clone(),
+            query,
+            signer: context.address.clone(),
+        }
+    }
+
+    /// Message to use in a WasmQuery::Smart
+    pub fn to_query_msg(&self) -> StdResult<Binary> {
+        to_binary(self)
+    }
+}
+
+/// Reads a tagged auth payload, {"permit": {...}} or {"viewing_key": {...}}, and authenticates it
+pub fn authenticate<T, A, K, F, const KEY_SIZE: usize>(
+    payload: &[u8],
+    api: &A,
+    msg_type: Option<String>,
+    stored_hash: F,
+) -> StdResult<CanonicalAddr>
+where
+    T: Serialize + DeserializeOwned,
+    A: Api,
+    K: ViewingKey<KEY_SIZE> + From<String>,
+    F: Fn(&CanonicalAddr) -> StdResult<Option<Vec<u8>>>,
+{
+    from_slice::<Auth<T>>(payload)?.authenticate::<A, K, F, KEY_SIZE>(api, msg_type, stored_hash)
+}
+
+#[cfg(test)]
+mod auth_tests {
+    use super::*;
+    use crate::permit::{bech32_to_canonical, PermitBuilder};
+    use crate::transaction::PermitSignature;
+    use cosmwasm_std::testing::mock_dependencies;
+    use cosmwasm_std::Uint128;
+
+    #[remain::sorted]
+    #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+    #[serde(rename_all = "snake_case")]
+    struct TestPermitMsg {
+        pub address: String,
+        pub some_number: Uint128,
+    }
+
+    struct Key(String);
+
+    impl std::fmt::Display for Key {
+        fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+            f.write_str(&self.0)
+        }
+    }
+
+    impl From<String> for Key {
+        fn from(key: String) -> Self {
+            Key(key)
+        }
+    }
+
+    impl ViewingKey<32> for Key {}
+
+    const ADDRESS: &str = "secret102nasmxnxvwp5agc4lp3flc6s23335xm8g7gn9";
+    const PUBKEY: &str = "A0qzJ3s16OKUfn1KFyh533vBnBOQIT0jm+R/FBobJCfa";
+    const SIGNED_TX: &str =
+        "4pZtghyHKHHmwiGNC5JD8JxCJiO+44j6GqaLPc19Q7lt85tr0IRZHYcnc0pkokIds8otxU9rcuvPXb0+etLyVA==";
+
+    fn stored_hash(addr: &CanonicalAddr) -> StdResult<Option<Vec<u8>>> {
+        if *addr == bech32_to_canonical(ADDRESS) {
+            Ok(Some(Key("password".to_string()).hash().to_vec()))
+        } else {
+            Ok(None)
+        }
+    }
+
+    #[test]
+    fn permit_auth() {
+        let auth = Auth::Permit(
+            PermitBuilder::new()
+                .params(TestPermitMsg {
+                    address: ADDRESS.to_string(),
+                    some_number: Uint128(10),
+                })
+                .chain_id("pulsar-1")
+                .signature(PermitSignature::from_base64(PUBKEY, SIGNED_TX).unwrap())
+                .build()
+                .unwrap(),
+        );
+
+        let deps = mock_dependencies(20, &[]);
+        let addr = auth
+            .authenticate::<_, Key, _, 32>(&deps.api, None, stored_hash)
+            .unwrap();
+        assert_eq!(addr, bech32_to_canonical(ADDRESS));
+    }
+
+    #[test]
+    fn viewing_key_auth() {
+        let deps = mock_dependencies(20, &[]);
+
+        let auth: Auth<TestPermitMsg> = Auth::ViewingKey {
+            address: HumanAddr(ADDRESS.to_string()),
+            key: "password".to_string(),
+        };
+        let addr = auth
+            .authenticate::<_, Key, _, 32>(&deps.api, None, stored_hash)
+            .unwrap();
+        assert_eq!(addr, bech32_to_canonical(ADDRESS));
+
+        let wrong_key: Auth<TestPermitMsg> = Auth::ViewingKey {
+            address: HumanAddr(ADDRESS.to_string()),
+            key: "wrong_password".to_string(),
+        };
+        assert!(wrong_key
+            .authenticate::<_, Key, _, 32>(&deps.api, None, stored_hash)
+            .is_err());
+
+        let no_key: Auth<TestPermitMsg> = Auth::ViewingKey {
+            address: HumanAddr("secret1w508d6qejxtdg4y5r3zarvary0c5xw7kccrnjy".to_string()),
+            key: "password".to_string(),
+        };
+        assert!(no_key
+            .authenticate::<_, Key, _, 32>(&deps.api, None, stored_hash)
+            .is_err());
+    }
+
+    #[test]
+    fn tagged_payloads() {
+        let deps = mock_dependencies(20, &[]);
+
+        let permit = format!(
+            r#"{{"permit":{{"params":{{"address":"{}","some_number":"10"}},"signature":{{"pub_key":{{"type":"tendermint/PubKeySecp256k1","value":"{}"}},"signature":"{}"}},"chain_id":"pulsar-1"}}}}"#,
+            ADDRESS, PUBKEY, SIGNED_TX
+        );
+        let addr = authenticate::<TestPermitMsg, _, Key, _, 32>(
+            permit.as_bytes(),
+            &deps.api,
+            None,
+            stored_hash,
+        )
+        .unwrap();
+        assert_eq!(addr, bech32_to_canonical(ADDRESS));
+
+        let viewing_key = format!(
+            r#"{{"viewing_key":{{"address":"{}","key":"password"}}}}"#,
+            ADDRESS
+        );
+        let addr = authenticate::<TestPermitMsg, _, Key, _, 32>(
+            viewing_key.as_bytes(),
+            &deps.api,
+            None,
+            stored_hash,
+        )
+        .unwrap();
+        assert_eq!(addr, bech32_to_canonical(ADDRESS));
+
+        assert!(authenticate::<TestPermitMsg, _, Key, _, 32>(
+            br#"{"password":{"key":"password"}}"#,
+            &deps.api,
+            None,
+            stored_hash,
+        )
+        .is_err());
+    }
+
+    #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+    #[serde(rename_all = "snake_case")]
+    struct BalanceQuery {
+        pub denom: String,
+    }
+
+    #[test]
+    fn forwarded_query() {
+        let permit = PermitBuilder::new()
+            .params(TestPermitMsg {
+                address: ADDRESS.to_string(),
+                some_number: Uint128(10),
+            })
+            .chain_id("pulsar-1")
+            .signature(PermitSignature::from_base64(PUBKEY, SIGNED_TX).unwrap())
+            .build()
+            .unwrap();
+
+        let deps = mock_dependencies(20, &[]);
+        let context = permit
+            .validate_full(&deps.api, None, |params| &params.address)
+            .unwrap();
+
+        let forwarded = ForwardedQuery::new(
+            &context,
+            BalanceQuery {
+                denom: "uscrt".to_string(),
+            },
+        );
+        assert_eq!(
+            forwarded,
+            ForwardedQuery {
+                chain_id: "pulsar-1".to_string(),
+                query: BalanceQuery {
+                    denom: "uscrt".to_string(),
+                },
+                signer: HumanAddr(ADDRESS.to_string()),
+            }
+        );
+
+        let msg = forwarded.to_query_msg().unwrap();
+        assert_eq!(
+            from_slice::<ForwardedQuery<BalanceQuery>>(msg.as_slice()).unwrap(),
+            forwarded
+        );
+    }
+}
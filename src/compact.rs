@@ -0,0 +1,2072 @@
+//! Compact canonical JSON, the exact bytes secretcli signs, so the sign-doc hash doesn't
+//! depend on cosmwasm's serializer. Structs, sequences, strings and integers are written the
+//! same way cosmwasm's to_vec writes them, the output differs for:
+//! - maps, their entries are always sorted by the UTF-16 code units of their key string,
+//!   i.e. integer keys are not in numeric order. cosmwasm's to_vec can't write maps at all
+//!   and serde_json keeps the map's iteration order
+//! - `#[serde(flatten)]` structs, serde writes them as maps so their fields get sorted too
+//! - floats, written in their canonical form where cosmwasm's to_vec can't write them
+//! - RawParams, written verbatim where other serializers write a JSON string
+//!
+//! Stack use grows with nesting only, every array, object or enum variant adds a few frames
+//! and nesting is capped by the max depth. Elements are written one after the other in a
+//! loop, so long flat arrays take the same stack as short ones.
+use cosmwasm_std::{Binary, StdError, StdResult};
+use schemars::gen::SchemaGenerator;
+use schemars::schema::Schema;
+use schemars::JsonSchema;
+use serde::de;
+use serde::{ser, Deserialize, Deserializer, Serialize};
+use std::any::type_name;
+use std::collections::HashSet;
+use std::fmt::{self, Display};
+use std::io;
+
+/// Number of bytes reserved for the output, fits most sign-docs
+const INITIAL_CAPACITY: usize = 1024;
+
+/// Smallest possible array element and map entry, i.e. `0,` and `"":0,`
+const MIN_ELEMENT_SIZE: usize = 2;
+const MIN_ENTRY_SIZE: usize = 5;
+
+/// Nesting allowed by to_vec, deeper values error instead of exhausting the stack
+pub const DEFAULT_MAX_DEPTH: usize = 128;
+
+/// Serializes the value as compact JSON, map entries are always sorted by key since their
+/// order depends on the map type and not on the signed params
+pub fn to_vec<T: Serialize + ?Sized>(value: &T) -> StdResult<Vec<u8>> {
+    to_vec_with_max_depth(value, DEFAULT_MAX_DEPTH)
+}
+
+/// Same as to_vec but errors once arrays, objects and enum variants nest deeper than max_depth
+pub fn to_vec_with_max_depth<T: Serialize + ?Sized>(
+    value: &T,
+    max_depth: usize,
+) -> StdResult<Vec<u8>> {
+    serialize(value, Serializer::new(max_depth))
+}
+
+/// Same as to_vec but reserves capacity bytes upfront instead of the default 1024,
+/// arrays and maps reserve more once they know their length
+pub fn to_vec_with_capacity<T: Serialize + ?Sized>(
+    value: &T,
+    capacity: usize,
+) -> StdResult<Vec<u8>> {
+    serialize(
+        value,
+        Serializer::with_capacity(capacity, DEFAULT_MAX_DEPTH),
+    )
+}
+
+/// Same as to_vec but serializes into buf, cleared first, and hands it back with its
+/// capacity so validating many permits can reuse one allocation
+pub fn to_vec_in<T: Serialize + ?Sized>(mut buf: Vec<u8>, value: &T) -> StdResult<Vec<u8>> {
+    buf.clear();
+    serialize(value, Serializer::with_buffer(buf, DEFAULT_MAX_DEPTH))
+}
+
+/// Same as to_vec but writes into writer while serializing instead of building the whole
+/// output, only the entries of an open map are held until they're sorted
+pub fn to_writer<W: io::Write, T: Serialize + ?Sized>(writer: &mut W, value: &T) -> StdResult<()> {
+    let mut serializer = Serializer::with_capacity(0, DEFAULT_MAX_DEPTH);
+    serializer.sink = Some(writer);
+    serialize(value, serializer).map(|_| ())
+}
+
+/// Same as to_vec but writes into a stack array and returns it with the number of bytes
+/// used, so small params serialize without allocating the output. Errors with "Buffer full"
+/// when the output is longer than N
+pub fn to_array<const N: usize, T: Serialize + ?Sized>(value: &T) -> StdResult<([u8; N], usize)> {
+    let mut writer = ArrayWriter {
+        array: [0; N],
+        len: 0,
+    };
+    to_writer(&mut writer, value)?;
+    Ok((writer.array, writer.len))
+}
+
+/// Fills an array, writes that don't fit are rejected whole
+struct ArrayWriter<const N: usize> {
+    array: [u8; N],
+    len: usize,
+}
+
+impl<const N: usize> io::Write for ArrayWriter<N> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let end = self.len + buf.len();
+        if end > N {
+            return Err(io::Error::new(
+                io::ErrorKind::WriteZero,
+                format!("Buffer full, the output doesn't fit in {} bytes", N),
+            ));
+        }
+        self.array[self.len..end].copy_from_slice(buf);
+        self.len = end;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Same as to_vec but object members are sorted by their UTF-16 code units as canonical JSON
+/// requires, this changes the signed bytes of any struct not declaring its fields sorted
+pub fn to_vec_sorted<T: Serialize + ?Sized>(value: &T) -> StdResult<Vec<u8>> {
+    let mut serializer = Serializer::new(DEFAULT_MAX_DEPTH);
+    serializer.sort_keys = true;
+    serialize(value, serializer)
+}
+
+/// Same as to_vec but errors on object keys and variant names holding control characters
+/// (U+0000 to U+001F) instead of escaping them into the signed bytes
+pub fn to_vec_strict<T: Serialize + ?Sized>(value: &T) -> StdResult<Vec<u8>> {
+    let mut serializer = Serializer::new(DEFAULT_MAX_DEPTH);
+    serializer.strict_keys = true;
+    serialize(value, serializer)
+}
+
+/// Same as to_vec but writes forward slashes in strings and map keys as \/, the way some
+/// wallets sign them. Changes the signed bytes of any string holding a /
+pub fn to_vec_escaping_slashes<T: Serialize + ?Sized>(value: &T) -> StdResult<Vec<u8>> {
+    let mut serializer = Serializer::new(DEFAULT_MAX_DEPTH);
+    serializer.escape_forward_slash = true;
+    serialize(value, serializer)
+}
+
+/// Errors on the first struct field written before the field preceding it, catches params
+/// missing #[remain::sorted]. Map keys are not checked, maps are always written sorted.
+/// Validation never runs this check, call it where the params types are declared
+pub fn assert_sorted_fields<T: Serialize + ?Sized>(value: &T) -> StdResult<()> {
+    let mut serializer = Serializer::new(DEFAULT_MAX_DEPTH);
+    serializer.check_order = true;
+    serialize(value, serializer).map(|_| ())
+}
+
+/// Same as to_vec with a final newline, some wallets display the signed message with it.
+/// A distinct mode as it changes the signed bytes and the Ethereum length prefix
+pub fn to_vec_with_trailing_newline<T: Serialize + ?Sized>(value: &T) -> StdResult<Vec<u8>> {
+    let mut bytes = to_vec(value)?;
+    bytes.push(b'\n');
+    Ok(bytes)
+}
+
+/// Same as to_string with a final newline, see to_vec_with_trailing_newline
+pub fn to_string_with_trailing_newline<T: Serialize + ?Sized>(value: &T) -> StdResult<String> {
+    String::from_utf8(to_vec_with_trailing_newline(value)?)
+        .map_err(|err| StdError::serialize_err(type_name::<T>(), err))
+}
+
+/// Wraps the message the way Ethereum's personal_sign does, the length counts every byte
+/// including a trailing newline
+pub fn eth_signed_message(message: &[u8]) -> Vec<u8> {
+    let mut signed = eth_signed_message_prefix(message.len());
+    signed.extend_from_slice(message);
+    signed
+}
+
+/// Bytes personal_sign writes before a message of len bytes
+pub fn eth_signed_message_prefix(len: usize) -> Vec<u8> {
+    format!("\x19Ethereum Signed Message:\n{}", len).into_bytes()
+}
+
+fn serialize<T: Serialize + ?Sized>(value: &T, mut serializer: Serializer) -> StdResult<Vec<u8>> {
+    value
+        .serialize(&mut serializer)
+        .map_err(|err| StdError::serialize_err(type_name::<T>(), err))?;
+    if let Some(err) = serializer.sink_error {
+        return Err(StdError::serialize_err(type_name::<T>(), err));
+    }
+    Ok(serializer.buf)
+}
+
+/// Serializes the value as a compact JSON string
+pub fn to_string<T: Serialize + ?Sized>(value: &T) -> StdResult<String> {
+    String::from_utf8(to_vec(value)?).map_err(|err| StdError::serialize_err(type_name::<T>(), err))
+}
+
+/// Newtype name the serializer recognizes as pre-serialized JSON
+const RAW_PARAMS_TOKEN: &str = "$query_authentication::RawParams";
+
+/// Pre-serialized JSON written verbatim into the sign-doc, lets relayers handle permits
+/// whose params they don't know. The JSON must already be compact with sorted keys, other
+/// serializers than this one see a plain string
+#[derive(Clone, Debug, PartialEq)]
+pub struct RawParams(String);
+
+impl RawParams {
+    /// Errors unless json holds exactly one JSON value, written the way to_vec_sorted
+    /// writes it. Floats, integers outside of i64 and u64 and duplicate keys are rejected
+    pub fn new(json: &[u8]) -> StdResult<Self> {
+        let json = String::from_utf8(json.to_vec())
+            .map_err(|err| StdError::parse_err("RawParams", err))?;
+        let value = Parser::parse(&json).map_err(|err| StdError::parse_err("RawParams", err))?;
+        if to_vec_sorted(&value)? != json.as_bytes() {
+            return Err(StdError::parse_err(
+                "RawParams",
+                "JSON must be compact with sorted keys",
+            ));
+        }
+        Ok(Self(json))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Serialize for RawParams {
+    fn serialize<S: ser::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_newtype_struct(RAW_PARAMS_TOKEN, &self.0)
+    }
+}
+
+/// Captures the params value of a permit parsed off-chain, i.e. by a relayer using serde_json,
+/// and checks it with RawParams::new. Needs a self-describing format, serde-json-wasm and so
+/// cosmwasm's from_slice can't deserialize unknown JSON
+impl<'de> Deserialize<'de> for RawParams {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = Json::deserialize(deserializer)?;
+        let mut json = vec![];
+        value.write_in_order(&mut json).map_err(de::Error::custom)?;
+        RawParams::new(&json).map_err(de::Error::custom)
+    }
+}
+
+/// JSON value RawParams are parsed into, serde-json-wasm can't deserialize unknown JSON
+enum Json {
+    Null,
+    Bool(bool),
+    Unsigned(u64),
+    Signed(i64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Serialize for Json {
+    fn serialize<S: ser::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use ser::SerializeMap;
+
+        match self {
+            Json::Null => serializer.serialize_unit(),
+            Json::Bool(v) => serializer.serialize_bool(*v),
+            Json::Unsigned(v) => serializer.serialize_u64(*v),
+            Json::Signed(v) => serializer.serialize_i64(*v),
+            Json::String(v) => serializer.serialize_str(v),
+            Json::Array(items) => items.serialize(serializer),
+            Json::Object(members) => {
+                let mut map = serializer.serialize_map(Some(members.len()))?;
+                for (key, value) in members {
+                    map.serialize_entry(key, value)?;
+                }
+                map.end()
+            }
+        }
+    }
+}
+
+impl Json {
+    /// Writes object members in the order they were read, so RawParams::new still sees
+    /// unsorted or duplicate keys
+    fn write_in_order(&self, out: &mut Vec<u8>) -> StdResult<()> {
+        match self {
+            Json::Array(items) => {
+                out.push(b'[');
+                for (index, item) in items.iter().enumerate() {
+                    if index > 0 {
+                        out.push(b',');
+                    }
+                    item.write_in_order(out)?;
+                }
+                out.push(b']');
+            }
+            Json::Object(members) => {
+                out.push(b'{');
+                for (index, (key, value)) in members.iter().enumerate() {
+                    if index > 0 {
+                        out.push(b',');
+                    }
+                    out.extend_from_slice(&to_vec(key)?);
+                    out.push(b':');
+                    value.write_in_order(out)?;
+                }
+                out.push(b'}');
+            }
+            scalar => out.extend_from_slice(&to_vec(scalar)?),
+        }
+        Ok(())
+    }
+}
+
+impl<'de> Deserialize<'de> for Json {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(JsonVisitor)
+    }
+}
+
+struct JsonVisitor;
+
+impl<'de> de::Visitor<'de> for JsonVisitor {
+    type Value = Json;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a JSON value")
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<Json, E> {
+        Ok(Json::Null)
+    }
+
+    fn visit_bool<E: de::Error>(self, v: bool) -> Result<Json, E> {
+        Ok(Json::Bool(v))
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<Json, E> {
+        // Same variants the parser picks, negative numbers only are signed
+        if v < 0 {
+            return Ok(Json::Signed(v));
+        }
+        Ok(Json::Unsigned(v as u64))
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<Json, E> {
+        Ok(Json::Unsigned(v))
+    }
+
+    fn visit_f64<E: de::Error>(self, _v: f64) -> Result<Json, E> {
+        Err(E::custom(unsupported("f64")))
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Json, E> {
+        Ok(Json::String(v.to_string()))
+    }
+
+    fn visit_string<E: de::Error>(self, v: String) -> Result<Json, E> {
+        Ok(Json::String(v))
+    }
+
+    fn visit_seq<A: de::SeqAccess<'de>>(self, mut seq: A) -> Result<Json, A::Error> {
+        let mut items = vec![];
+        while let Some(item) = seq.next_element()? {
+            items.push(item);
+        }
+        Ok(Json::Array(items))
+    }
+
+    fn visit_map<A: de::MapAccess<'de>>(self, mut map: A) -> Result<Json, A::Error> {
+        let mut members = vec![];
+        while let Some(member) = map.next_entry()? {
+            members.push(member);
+        }
+        Ok(Json::Object(members))
+    }
+}
+
+/// Strict JSON parser, whitespace is never skipped since canonical JSON has none
+struct Parser<'a> {
+    json: &'a str,
+    pos: usize,
+    /// Containers currently open
+    depth: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn parse(json: &'a str) -> Result<Json, Error> {
+        let mut parser = Parser {
+            json,
+            pos: 0,
+            depth: 0,
+        };
+        let value = parser.value()?;
+        if parser.pos != json.len() {
+            return Err(parser.unexpected());
+        }
+        Ok(value)
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.json.as_bytes().get(self.pos).copied()
+    }
+
+    fn next(&mut self) -> Result<u8, Error> {
+        let c = self
+            .peek()
+            .ok_or_else(|| Error("Unexpected end of JSON".to_string()))?;
+        self.pos += 1;
+        Ok(c)
+    }
+
+    fn expect(&mut self, expected: &str) -> Result<(), Error> {
+        if !self.json[self.pos..].starts_with(expected) {
+            return Err(self.unexpected());
+        }
+        self.pos += expected.len();
+        Ok(())
+    }
+
+    fn unexpected(&self) -> Error {
+        match self.peek() {
+            Some(_) => Error(format!("Unexpected character at position {}", self.pos)),
+            None => Error("Unexpected end of JSON".to_string()),
+        }
+    }
+
+    fn value(&mut self) -> Result<Json, Error> {
+        match self.peek() {
+            Some(b'n') => self.expect("null").map(|_| Json::Null),
+            Some(b't') => self.expect("true").map(|_| Json::Bool(true)),
+            Some(b'f') => self.expect("false").map(|_| Json::Bool(false)),
+            Some(b'"') => self.string().map(Json::String),
+            Some(b'-' | b'0'..=b'9') => self.number(),
+            Some(b'[') => self.array(),
+            Some(b'{') => self.object(),
+            _ => Err(self.unexpected()),
+        }
+    }
+
+    fn number(&mut self) -> Result<Json, Error> {
+        let start = self.pos;
+        if self.peek() == Some(b'-') {
+            self.pos += 1;
+        }
+        let digits_start = self.pos;
+        while let Some(b'0'..=b'9') = self.peek() {
+            self.pos += 1;
+        }
+        if self.pos == digits_start {
+            return Err(self.unexpected());
+        }
+        if let Some(b'.' | b'e' | b'E') = self.peek() {
+            return Err(unsupported("f64"));
+        }
+
+        let number = &self.json[start..self.pos];
+        let parsed = if start == digits_start {
+            number.parse().map(Json::Unsigned)
+        } else {
+            number.parse().map(Json::Signed)
+        };
+        parsed.map_err(|_| Error(format!("Number {} is out of range", number)))
+    }
+
+    fn string(&mut self) -> Result<String, Error> {
+        self.pos += 1;
+        let mut string = String::new();
+        loop {
+            // Quotes, backslashes and control chars are ASCII, so runs end on char boundaries
+            let start = self.pos;
+            while let Some(c) = self.peek() {
+                if c == b'"' || c == b'\\' || c < 0x20 {
+                    break;
+                }
+                self.pos += 1;
+            }
+            string.push_str(&self.json[start..self.pos]);
+
+            match self.next()? {
+                b'"' => return Ok(string),
+                b'\\' => string.push(self.escape()?),
+                _ => {
+                    return Err(Error(format!(
+                        "Unescaped control character at position {}",
+                        self.pos - 1
+                    )))
+                }
+            }
+        }
+    }
+
+    /// Surrogate pairs are rejected, canonical JSON writes those chars unescaped anyway
+    fn escape(&mut self) -> Result<char, Error> {
+        let start = self.pos - 1;
+        let c = match self.next()? {
+            b'"' => '"',
+            b'\\' => '\\',
+            b'/' => '/',
+            b'b' => '\u{0008}',
+            b'f' => '\u{000C}',
+            b'n' => '\n',
+            b'r' => '\r',
+            b't' => '\t',
+            b'u' => {
+                let c = self
+                    .json
+                    .get(self.pos..self.pos + 4)
+                    .filter(|hex| hex.bytes().all(|c| c.is_ascii_hexdigit()))
+                    .and_then(|hex| char::from_u32(u32::from_str_radix(hex, 16).ok()?))
+                    .ok_or_else(|| Error(format!("Invalid escape at position {}", start)))?;
+                self.pos += 4;
+                c
+            }
+            _ => return Err(Error(format!("Invalid escape at position {}", start))),
+        };
+        Ok(c)
+    }
+
+    fn enter(&mut self) -> Result<(), Error> {
+        if self.depth >= DEFAULT_MAX_DEPTH {
+            return Err(Error("max depth exceeded".to_string()));
+        }
+        self.depth += 1;
+        self.pos += 1;
+        Ok(())
+    }
+
+    /// Consumes the separator after an element, true once the container is closed
+    fn close(&mut self, closing: u8) -> Result<bool, Error> {
+        match self.peek() {
+            Some(b',') => {
+                self.pos += 1;
+                Ok(false)
+            }
+            Some(c) if c == closing => {
+                self.pos += 1;
+                self.depth -= 1;
+                Ok(true)
+            }
+            _ => Err(self.unexpected()),
+        }
+    }
+
+    fn array(&mut self) -> Result<Json, Error> {
+        self.enter()?;
+        let mut items = vec![];
+        if self.peek() == Some(b']') {
+            self.close(b']')?;
+            return Ok(Json::Array(items));
+        }
+        loop {
+            items.push(self.value()?);
+            if self.close(b']')? {
+                return Ok(Json::Array(items));
+            }
+        }
+    }
+
+    fn object(&mut self) -> Result<Json, Error> {
+        self.enter()?;
+        let mut members = vec![];
+        if self.peek() == Some(b'}') {
+            self.close(b'}')?;
+            return Ok(Json::Object(members));
+        }
+        let mut keys = HashSet::new();
+        loop {
+            if self.peek() != Some(b'"') {
+                return Err(self.unexpected());
+            }
+            let key = self.string()?;
+            // Sorting keeps both members, so the round trip can't catch these
+            if !keys.insert(key.clone()) {
+                return Err(Error(format!("Duplicate key {:?}", key)));
+            }
+            self.expect(":")?;
+            members.push((key, self.value()?));
+            if self.close(b'}')? {
+                return Ok(Json::Object(members));
+            }
+        }
+    }
+}
+
+/// Bytes rendered as a lowercase 0x prefixed hex string instead of Binary's base64,
+/// for params checked by Ethereum tooling. Signs different bytes than Binary
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct HexBinary(pub Vec<u8>);
+
+impl HexBinary {
+    pub fn to_hex(&self) -> String {
+        let mut hex = "0x".to_string();
+        for byte in &self.0 {
+            hex.push_str(&format!("{:02x}", byte));
+        }
+        hex
+    }
+
+    /// Accepts upper and lowercase digits, the 0x prefix is required
+    pub fn from_hex(hex: &str) -> StdResult<Self> {
+        let digits = hex
+            .strip_prefix("0x")
+            .ok_or_else(|| StdError::parse_err("HexBinary", "Missing 0x prefix"))?;
+        if digits.len() % 2 != 0 {
+            return Err(StdError::parse_err("HexBinary", "Odd number of hex digits"));
+        }
+        // from_str_radix would also take a sign
+        if !digits.bytes().all(|c| c.is_ascii_hexdigit()) {
+            return Err(StdError::parse_err("HexBinary", "Invalid hex digit"));
+        }
+
+        let bytes = (0..digits.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&digits[i..i + 2], 16).unwrap())
+            .collect();
+        Ok(HexBinary(bytes))
+    }
+}
+
+impl From<Binary> for HexBinary {
+    fn from(binary: Binary) -> Self {
+        Self(binary.0)
+    }
+}
+
+impl Serialize for HexBinary {
+    fn serialize<S: ser::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_hex())
+    }
+}
+
+impl<'de> Deserialize<'de> for HexBinary {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let hex = String::deserialize(deserializer)?;
+        HexBinary::from_hex(&hex).map_err(de::Error::custom)
+    }
+}
+
+impl JsonSchema for HexBinary {
+    fn schema_name() -> String {
+        "HexBinary".to_string()
+    }
+
+    fn json_schema(gen: &mut SchemaGenerator) -> Schema {
+        String::json_schema(gen)
+    }
+}
+
+/// Serializer error shared by the sign-doc walkers of this crate
+#[derive(Debug)]
+pub(crate) struct Error(pub(crate) String);
+
+impl Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl ser::Error for Error {
+    fn custom<T: Display>(msg: T) -> Self {
+        Error(msg.to_string())
+    }
+}
+
+fn unsupported(kind: &str) -> Error {
+    Error(format!("{} is not supported in a sign-doc", kind))
+}
+
+/// Canonical JSON number, see https://gibson042.github.io/canonicaljson-spec/. Integers are
+/// written without a decimal point or exponent and zero without its sign, any other number
+/// in E notation with a single leading digit. Takes the shortest digits that round-trip,
+/// the ones `{:e}` prints, so 0.1 becomes 1.0E-1 and not its exact binary value
+pub(crate) fn canonical_number<F: fmt::LowerExp>(v: F) -> String {
+    let scientific = format!("{:e}", v);
+    let (sign, scientific) = match scientific.strip_prefix('-') {
+        Some(scientific) => ("-", scientific),
+        None => ("", scientific.as_str()),
+    };
+    let (mantissa, exponent) = scientific
+        .split_once('e')
+        .expect("{:e} always writes an exponent");
+    let exponent: i32 = exponent.parse().expect("{:e} writes an integer exponent");
+    let digits = mantissa.replace('.', "");
+    if digits == "0" {
+        return "0".to_string();
+    }
+
+    let fraction_digits = digits.len() as i32 - 1;
+    if exponent >= fraction_digits {
+        let zeros = "0".repeat((exponent - fraction_digits) as usize);
+        return format!("{}{}{}", sign, digits, zeros);
+    }
+    let fraction = if fraction_digits == 0 {
+        "0"
+    } else {
+        &digits[1..]
+    };
+    format!("{}{}.{}E{}", sign, &digits[..1], fraction, exponent)
+}
+
+struct Serializer<'w> {
+    /// Output, or only the members of the sorted objects still open when writing to a sink
+    buf: Vec<u8>,
+    /// Receives the output as it's written instead of buf
+    sink: Option<&'w mut dyn io::Write>,
+    /// First error of the sink, later writes are dropped
+    sink_error: Option<io::Error>,
+    /// Open objects collecting their members to sort them
+    collecting: usize,
+    /// Containers currently open
+    depth: usize,
+    max_depth: usize,
+    /// Write struct members sorted instead of in declaration order, maps are always sorted
+    sort_keys: bool,
+    /// Reject keys with control characters
+    strict_keys: bool,
+    /// Reject struct fields written out of alphabetical order
+    check_order: bool,
+    /// Write / as \/ in strings, JSON allows both
+    escape_forward_slash: bool,
+}
+
+impl<'w> Serializer<'w> {
+    fn new(max_depth: usize) -> Self {
+        Self::with_capacity(INITIAL_CAPACITY, max_depth)
+    }
+
+    fn with_capacity(capacity: usize, max_depth: usize) -> Self {
+        Self::with_buffer(Vec::with_capacity(capacity), max_depth)
+    }
+
+    fn with_buffer(buf: Vec<u8>, max_depth: usize) -> Self {
+        Self {
+            buf,
+            sink: None,
+            sink_error: None,
+            collecting: 0,
+            depth: 0,
+            max_depth,
+            sort_keys: false,
+            strict_keys: false,
+            check_order: false,
+            escape_forward_slash: false,
+        }
+    }
+
+    fn enter(&mut self) -> Result<(), Error> {
+        if self.depth >= self.max_depth {
+            return Err(Error("max depth exceeded".to_string()));
+        }
+        self.depth += 1;
+        Ok(())
+    }
+
+    fn exit(&mut self) {
+        self.depth -= 1;
+    }
+
+    /// Writes into the sink unless a sorted object is collecting its members
+    fn write_bytes(&mut self, bytes: &[u8]) {
+        match &mut self.sink {
+            Some(sink) if self.collecting == 0 => {
+                if self.sink_error.is_none() {
+                    self.sink_error = sink.write_all(bytes).err();
+                }
+            }
+            _ => self.buf.extend_from_slice(bytes),
+        }
+    }
+
+    fn write_byte(&mut self, byte: u8) {
+        self.write_bytes(&[byte]);
+    }
+
+    /// Hands the members of the last sorted object over to the sink once it's closed
+    fn flush(&mut self) {
+        if self.collecting == 0 && self.sink.is_some() && !self.buf.is_empty() {
+            let sorted = std::mem::take(&mut self.buf);
+            self.write_bytes(&sorted);
+            self.buf = sorted;
+            self.buf.clear();
+        }
+    }
+
+    /// Reserves room for len elements of at least min_size bytes each, sinks reserve nothing
+    fn reserve_hint(&mut self, len: Option<usize>, min_size: usize) {
+        if self.sink.is_some() {
+            return;
+        }
+        if let Some(len) = len {
+            self.buf.reserve(len.saturating_mul(min_size));
+        }
+    }
+
+    fn write_unsigned(&mut self, v: u64) {
+        self.write_bytes(v.to_string().as_bytes());
+    }
+
+    fn write_signed(&mut self, v: i64) {
+        self.write_bytes(v.to_string().as_bytes());
+    }
+
+    /// Minimal escaping, see https://gibson042.github.io/canonicaljson-spec/
+    fn write_str(&mut self, v: &str) {
+        self.write_byte(b'"');
+        for c in v.chars() {
+            match c {
+                '\\' => self.write_bytes(b"\\\\"),
+                '"' => self.write_bytes(b"\\\""),
+                '/' if self.escape_forward_slash => self.write_bytes(b"\\/"),
+                '\u{0008}' => self.write_bytes(b"\\b"),
+                '\u{0009}' => self.write_bytes(b"\\t"),
+                '\u{000A}' => self.write_bytes(b"\\n"),
+                '\u{000C}' => self.write_bytes(b"\\f"),
+                '\u{000D}' => self.write_bytes(b"\\r"),
+                '\u{0000}'..='\u{001F}' => {
+                    self.write_bytes(format!("\\u{:04X}", c as u32).as_bytes());
+                }
+                _ => self.write_bytes(c.encode_utf8(&mut [0; 4]).as_bytes()),
+            }
+        }
+        self.write_byte(b'"');
+    }
+
+    fn check_key(&self, key: &str) -> Result<(), Error> {
+        if self.strict_keys && key.chars().any(|c| c <= '\u{001F}') {
+            return Err(Error(format!("Key {:?} contains a control character", key)));
+        }
+        Ok(())
+    }
+
+    /// Opens {"variant": around an enum value
+    fn open_variant(&mut self, variant: &str) -> Result<(), Error> {
+        self.check_key(variant)?;
+        self.enter()?;
+        self.write_byte(b'{');
+        self.write_str(variant);
+        self.write_byte(b':');
+        Ok(())
+    }
+}
+
+struct Compound<'a, 'w> {
+    ser: &'a mut Serializer<'w>,
+    first: bool,
+    /// Closing brackets still to be written
+    closing: &'static [u8],
+    /// Key and start of every member written so far, only kept for maps and for structs when
+    /// sorting keys
+    members: Option<Vec<(String, usize)>>,
+    /// Last struct field written, only kept when checking the field order
+    last_field: Option<&'static str>,
+}
+
+impl<'a, 'w> Compound<'a, 'w> {
+    fn new(
+        ser: &'a mut Serializer<'w>,
+        opening: u8,
+        closing: &'static [u8],
+    ) -> Result<Self, Error> {
+        ser.enter()?;
+        ser.write_byte(opening);
+        let sort = opening == b'{' && ser.sort_keys;
+        let mut compound = Self {
+            ser,
+            first: true,
+            closing,
+            members: None,
+            last_field: None,
+        };
+        if sort {
+            compound.collect_members();
+        }
+        Ok(compound)
+    }
+
+    /// Keeps the members in the buffer until they can be sorted
+    fn collect_members(&mut self) {
+        if self.members.is_none() {
+            self.members = Some(vec![]);
+            self.ser.collecting += 1;
+        }
+    }
+
+    fn separator(&mut self) {
+        if !self.first {
+            self.ser.write_byte(b',');
+        }
+        self.first = false;
+    }
+
+    /// Returns before the next element starts, elements never stack on top of each other
+    #[inline]
+    fn element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.separator();
+        value.serialize(&mut *self.ser)
+    }
+
+    /// Starts a member, separators of sorted members are written once they're in order
+    fn key(&mut self, key: &str) {
+        match &mut self.members {
+            Some(members) => members.push((key.to_string(), self.ser.buf.len())),
+            None => self.separator(),
+        }
+    }
+
+    fn field<T: Serialize + ?Sized>(&mut self, key: &'static str, value: &T) -> Result<(), Error> {
+        self.ser.check_key(key)?;
+        if self.ser.check_order {
+            if let Some(last) = self.last_field.filter(|last| key < *last) {
+                return Err(Error(format!(
+                    "Field {} is out of order, it must come before {}",
+                    key, last
+                )));
+            }
+            self.last_field = Some(key);
+        }
+        self.key(key);
+        // Keys are written as declared, same as cosmwasm
+        self.ser.write_byte(b'"');
+        self.ser.write_bytes(key.as_bytes());
+        self.ser.write_bytes(b"\":");
+        value.serialize(&mut *self.ser)
+    }
+
+    fn finish(mut self) -> Result<(), Error> {
+        let members = self.members.take();
+        let collected = members.is_some();
+        if let Some(members) = members {
+            self.sort_members(members);
+        }
+        self.ser.write_bytes(self.closing);
+        // Variants close their wrapping object as well
+        for _ in self.closing {
+            self.ser.exit();
+        }
+        if collected {
+            self.ser.collecting -= 1;
+            self.ser.flush();
+        }
+        Ok(())
+    }
+
+    /// Rewrites the members in key order, duplicate keys keep their order
+    fn sort_members(&mut self, members: Vec<(String, usize)>) {
+        let start = match members.first() {
+            Some((_, start)) => *start,
+            None => return,
+        };
+        let written = self.ser.buf.split_off(start);
+
+        // Each member runs until the next one starts
+        let mut ranges: Vec<(&str, usize, usize)> = members
+            .iter()
+            .enumerate()
+            .map(|(i, (key, pos))| {
+                let end = members
+                    .get(i + 1)
+                    .map_or(start + written.len(), |next| next.1);
+                (key.as_str(), pos - start, end - start)
+            })
+            .collect();
+        ranges.sort_by(|a, b| a.0.encode_utf16().cmp(b.0.encode_utf16()));
+
+        for (i, (_, from, to)) in ranges.into_iter().enumerate() {
+            if i > 0 {
+                self.ser.write_byte(b',');
+            }
+            self.ser.write_bytes(&written[from..to]);
+        }
+    }
+}
+
+impl<'a, 'w> ser::Serializer for &'a mut Serializer<'w> {
+    type Ok = ();
+    type Error = Error;
+    type SerializeSeq = Compound<'a, 'w>;
+    type SerializeTuple = Compound<'a, 'w>;
+    type SerializeTupleStruct = Compound<'a, 'w>;
+    type SerializeTupleVariant = Compound<'a, 'w>;
+    type SerializeMap = Compound<'a, 'w>;
+    type SerializeStruct = Compound<'a, 'w>;
+    type SerializeStructVariant = Compound<'a, 'w>;
+
+    fn serialize_bool(self, v: bool) -> Result<(), Error> {
+        self.write_bytes(if v { b"true" as &[u8] } else { b"false" });
+        Ok(())
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<(), Error> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<(), Error> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<(), Error> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<(), Error> {
+        self.write_signed(v);
+        Ok(())
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<(), Error> {
+        self.serialize_u64(v as u64)
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<(), Error> {
+        self.serialize_u64(v as u64)
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<(), Error> {
+        self.serialize_u64(v as u64)
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<(), Error> {
+        self.write_unsigned(v);
+        Ok(())
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<(), Error> {
+        if !v.is_finite() {
+            return Err(Error(format!("{} is not a JSON number", v)));
+        }
+        self.write_bytes(canonical_number(v).as_bytes());
+        Ok(())
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<(), Error> {
+        if !v.is_finite() {
+            return Err(Error(format!("{} is not a JSON number", v)));
+        }
+        self.write_bytes(canonical_number(v).as_bytes());
+        Ok(())
+    }
+
+    fn serialize_char(self, v: char) -> Result<(), Error> {
+        // One char string, escaped like any other
+        self.write_str(v.encode_utf8(&mut [0; 4]));
+        Ok(())
+    }
+
+    fn serialize_str(self, v: &str) -> Result<(), Error> {
+        self.write_str(v);
+        Ok(())
+    }
+
+    /// Standard base64 string, the same way cosmwasm's Binary renders
+    fn serialize_bytes(self, v: &[u8]) -> Result<(), Error> {
+        self.write_str(&Binary::from(v).to_base64());
+        Ok(())
+    }
+
+    fn serialize_none(self) -> Result<(), Error> {
+        self.serialize_unit()
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<(), Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<(), Error> {
+        self.write_bytes(b"null");
+        Ok(())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<(), Error> {
+        self.serialize_unit()
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<(), Error> {
+        self.write_str(variant);
+        Ok(())
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        name: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        if name == RAW_PARAMS_TOKEN {
+            // Only RawParams uses the token and it always holds a string
+            let json = value.serialize(MapKey)?;
+            self.write_bytes(json.as_bytes());
+            return Ok(());
+        }
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        self.open_variant(variant)?;
+        value.serialize(&mut *self)?;
+        self.write_byte(b'}');
+        self.exit();
+        Ok(())
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<Compound<'a, 'w>, Error> {
+        self.reserve_hint(len, MIN_ELEMENT_SIZE);
+        Compound::new(self, b'[', b"]")
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Compound<'a, 'w>, Error> {
+        Compound::new(self, b'[', b"]")
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Compound<'a, 'w>, Error> {
+        // Same array as a tuple
+        Compound::new(self, b'[', b"]")
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Compound<'a, 'w>, Error> {
+        self.open_variant(variant)?;
+        Compound::new(self, b'[', b"]}")
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<Compound<'a, 'w>, Error> {
+        self.reserve_hint(len, MIN_ENTRY_SIZE);
+        let mut map = Compound::new(self, b'{', b"}")?;
+        map.collect_members();
+        Ok(map)
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Compound<'a, 'w>, Error> {
+        Compound::new(self, b'{', b"}")
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Compound<'a, 'w>, Error> {
+        self.open_variant(variant)?;
+        Compound::new(self, b'{', b"}}")
+    }
+}
+
+impl<'a, 'w> ser::SerializeSeq for Compound<'a, 'w> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.finish()
+    }
+}
+
+impl<'a, 'w> ser::SerializeTuple for Compound<'a, 'w> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.finish()
+    }
+}
+
+impl<'a, 'w> ser::SerializeTupleStruct for Compound<'a, 'w> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.finish()
+    }
+}
+
+impl<'a, 'w> ser::SerializeTupleVariant for Compound<'a, 'w> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.finish()
+    }
+}
+
+impl<'a, 'w> ser::SerializeMap for Compound<'a, 'w> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), Error> {
+        let key = key.serialize(MapKey)?;
+        self.ser.check_key(&key)?;
+        self.key(&key);
+        self.ser.write_str(&key);
+        self.ser.write_byte(b':');
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        value.serialize(&mut *self.ser)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.finish()
+    }
+}
+
+impl<'a, 'w> ser::SerializeStruct for Compound<'a, 'w> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        self.field(key, value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.finish()
+    }
+}
+
+impl<'a, 'w> ser::SerializeStructVariant for Compound<'a, 'w> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        self.field(key, value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.finish()
+    }
+}
+
+/// Turns map keys into strings, only strings and values that read
+/// unambiguously as one (chars, integers, unit variants) are accepted
+struct MapKey;
+
+fn key_error() -> Error {
+    Error("Map keys must be strings, chars or integers".to_string())
+}
+
+impl MapKey {
+    fn display<T: Display>(self, v: T) -> Result<String, Error> {
+        Ok(v.to_string())
+    }
+}
+
+impl ser::Serializer for MapKey {
+    type Ok = String;
+    type Error = Error;
+    type SerializeSeq = ser::Impossible<String, Error>;
+    type SerializeTuple = ser::Impossible<String, Error>;
+    type SerializeTupleStruct = ser::Impossible<String, Error>;
+    type SerializeTupleVariant = ser::Impossible<String, Error>;
+    type SerializeMap = ser::Impossible<String, Error>;
+    type SerializeStruct = ser::Impossible<String, Error>;
+    type SerializeStructVariant = ser::Impossible<String, Error>;
+
+    fn serialize_bool(self, _v: bool) -> Result<String, Error> {
+        Err(key_error())
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<String, Error> {
+        self.display(v)
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<String, Error> {
+        self.display(v)
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<String, Error> {
+        self.display(v)
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<String, Error> {
+        self.display(v)
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<String, Error> {
+        self.display(v)
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<String, Error> {
+        self.display(v)
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<String, Error> {
+        self.display(v)
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<String, Error> {
+        self.display(v)
+    }
+
+    fn serialize_f32(self, _v: f32) -> Result<String, Error> {
+        Err(key_error())
+    }
+
+    fn serialize_f64(self, _v: f64) -> Result<String, Error> {
+        Err(key_error())
+    }
+
+    fn serialize_char(self, v: char) -> Result<String, Error> {
+        self.display(v)
+    }
+
+    fn serialize_str(self, v: &str) -> Result<String, Error> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_bytes(self, _v: &[u8]) -> Result<String, Error> {
+        Err(key_error())
+    }
+
+    fn serialize_none(self) -> Result<String, Error> {
+        Err(key_error())
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, _value: &T) -> Result<String, Error> {
+        Err(key_error())
+    }
+
+    fn serialize_unit(self) -> Result<String, Error> {
+        Err(key_error())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<String, Error> {
+        Err(key_error())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<String, Error> {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<String, Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<String, Error> {
+        Err(key_error())
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, Error> {
+        Err(key_error())
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, Error> {
+        Err(key_error())
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct, Error> {
+        Err(key_error())
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, Error> {
+        Err(key_error())
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, Error> {
+        Err(key_error())
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStruct, Error> {
+        Err(key_error())
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, Error> {
+        Err(key_error())
+    }
+}
+
+#[cfg(test)]
+mod compact_tests {
+    use super::*;
+    use crate::permit::PermitBuilder;
+    use crate::transaction::{PermitSignature, PubKey, SignedTxRef};
+    use cosmwasm_std::{from_slice, Binary, HumanAddr, Uint128};
+    use serde::{Deserialize, Serialize};
+    use std::collections::BTreeMap;
+
+    #[remain::sorted]
+    #[derive(Serialize)]
+    struct Params {
+        address: HumanAddr,
+        amount: Uint128,
+        data: Binary,
+        enabled: bool,
+        memo: Option<String>,
+        negative: i32,
+        outcome: Result<u64, String>,
+        scopes: Vec<String>,
+        shape: Shape,
+        tuple: (u8, String),
+        unit: (),
+    }
+
+    #[derive(Serialize)]
+    #[serde(rename_all = "snake_case")]
+    enum Shape {
+        Rect { height: u32, width: u32 },
+        Line(u32, u32),
+    }
+
+    fn params() -> Params {
+        Params {
+            address: HumanAddr("secret102nasmxnxvwp5agc4lp3flc6s23335xm8g7gn9".to_string()),
+            amount: Uint128(10),
+            data: Binary(vec![1, 2, 3]),
+            enabled: true,
+            memo: None,
+            negative: i32::MIN,
+            outcome: Err("quote \" backslash \\ tab \t bell \u{7} ☃".to_string()),
+            scopes: vec!["balance".to_string(), "".to_string()],
+            shape: Shape::Rect {
+                height: 1,
+                width: 2,
+            },
+            tuple: (255, "history".to_string()),
+            unit: (),
+        }
+    }
+
+    #[test]
+    fn matches_cosmwasm() {
+        let mut params = params();
+        assert_eq!(
+            to_string(&params).unwrap(),
+            concat!(
+                r#"{"address":"secret102nasmxnxvwp5agc4lp3flc6s23335xm8g7gn9","amount":"10","#,
+                r#""data":"AQID","enabled":true,"memo":null,"negative":-2147483648,"#,
+                r#""outcome":{"Err":"quote \" backslash \\ tab \t bell \u0007 ☃"},"#,
+                r#""scopes":["balance",""],"shape":{"rect":{"height":1,"width":2}},"#,
+                r#""tuple":[255,"history"],"unit":null}"#
+            )
+        );
+        assert_eq!(
+            to_vec(&params).unwrap(),
+            cosmwasm_std::to_vec(&params).unwrap()
+        );
+
+        params.shape = Shape::Line(3, 4);
+        params.outcome = Ok(u64::MAX);
+        params.memo = Some("memo".to_string());
+        assert_eq!(
+            to_vec(&params).unwrap(),
+            cosmwasm_std::to_vec(&params).unwrap()
+        );
+    }
+
+    #[test]
+    fn permit_sign_doc() {
+        let permit = PermitBuilder::new()
+            .params(params())
+            .chain_id("pulsar-1")
+            .sequence(Uint128(3))
+            .signature(PermitSignature {
+                pub_key: PubKey::new(Binary(vec![2; 33])),
+                signature: Binary(vec![0; 64]),
+            })
+            .memo("b64Encoded")
+            .expires(1000)
+            .action("query_balance")
+            .build()
+            .unwrap();
+
+        let sign_doc = SignedTxRef::from_permit(&permit, None);
+        assert_eq!(
+            to_vec(&sign_doc).unwrap(),
+            cosmwasm_std::to_vec(&sign_doc).unwrap()
+        );
+        assert_eq!(
+            to_vec(&permit).unwrap(),
+            cosmwasm_std::to_vec(&permit).unwrap()
+        );
+    }
+
+    #[derive(Serialize)]
+    struct Point(u32, u32);
+
+    #[derive(Serialize)]
+    struct Wrapped(Point);
+
+    #[remain::sorted]
+    #[derive(Serialize)]
+    struct Path {
+        end: Wrapped,
+        start: Point,
+    }
+
+    #[test]
+    fn tuple_structs() {
+        assert_eq!(to_string(&Point(1, 2)).unwrap(), "[1,2]");
+        assert_eq!(to_string(&Wrapped(Point(3, 4))).unwrap(), "[3,4]");
+        assert_eq!(to_string(&(1u32, 2u32)).unwrap(), "[1,2]");
+        assert_eq!(
+            to_string(&Path {
+                end: Wrapped(Point(5, 6)),
+                start: Point(0, 0),
+            })
+            .unwrap(),
+            r#"{"end":[5,6],"start":[0,0]}"#
+        );
+    }
+
+    #[test]
+    fn chars() {
+        assert_eq!(to_string(&'a').unwrap(), r#""a""#);
+        assert_eq!(to_string(&'"').unwrap(), r#""\"""#);
+        assert_eq!(to_string(&'\\').unwrap(), r#""\\""#);
+        assert_eq!(to_string(&'\n').unwrap(), r#""\n""#);
+        assert_eq!(to_string(&'\u{1}').unwrap(), r#""\u0001""#);
+        assert_eq!(to_string(&'💣').unwrap(), "\"💣\"");
+    }
+
+    /// Serializes through serialize_bytes like serde_bytes does
+    struct RawBytes(Vec<u8>);
+
+    impl Serialize for RawBytes {
+        fn serialize<S: ser::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.serialize_bytes(&self.0)
+        }
+    }
+
+    #[remain::sorted]
+    #[derive(Serialize)]
+    struct BytesMsg {
+        binary: Binary,
+        raw: RawBytes,
+    }
+
+    #[test]
+    fn bytes() {
+        let msg = BytesMsg {
+            binary: Binary(vec![0xfb, 0xff, 0x00, 0x01]),
+            raw: RawBytes(vec![0xfb, 0xff, 0x00, 0x01]),
+        };
+        assert_eq!(
+            to_string(&msg).unwrap(),
+            r#"{"binary":"+/8AAQ==","raw":"+/8AAQ=="}"#
+        );
+        assert_eq!(to_string(&RawBytes(vec![])).unwrap(), r#""""#);
+    }
+
+    #[remain::sorted]
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct HexMsg {
+        base64: Binary,
+        hex: HexBinary,
+    }
+
+    #[test]
+    fn hex_bytes() {
+        let bytes = vec![0xfb, 0xff, 0x00, 0x01];
+        let msg = HexMsg {
+            base64: Binary(bytes.clone()),
+            hex: HexBinary::from(Binary(bytes)),
+        };
+
+        let json = to_vec(&msg).unwrap();
+        assert_eq!(json, br#"{"base64":"+/8AAQ==","hex":"0xfbff0001"}"#);
+        assert_eq!(json, cosmwasm_std::to_vec(&msg).unwrap());
+        assert_eq!(from_slice::<HexMsg>(&json).unwrap(), msg);
+
+        assert_eq!(to_string(&HexBinary(vec![])).unwrap(), r#""0x""#);
+        assert_eq!(
+            HexBinary::from_hex("0xFBff0001").unwrap().0,
+            vec![0xfb, 0xff, 0x00, 0x01]
+        );
+        assert!(HexBinary::from_hex("fbff").is_err());
+        assert!(HexBinary::from_hex("0xfbf").is_err());
+        assert!(HexBinary::from_hex("0xzz").is_err());
+        assert!(HexBinary::from_hex("0x+1").is_err());
+    }
+
+    /// Arrays nested the given amount of times
+    struct Nested(usize);
+
+    impl Serialize for Nested {
+        fn serialize<S: ser::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            use serde::ser::SerializeSeq;
+
+            let mut seq = serializer.serialize_seq(Some(1))?;
+            if self.0 > 1 {
+                seq.serialize_element(&Nested(self.0 - 1))?;
+            }
+            seq.end()
+        }
+    }
+
+    #[test]
+    fn max_depth() {
+        assert_eq!(to_string(&Nested(3)).unwrap(), "[[[]]]");
+        assert!(to_vec(&Nested(DEFAULT_MAX_DEPTH)).is_ok());
+        assert_eq!(
+            to_vec(&Nested(10_000)).unwrap_err(),
+            StdError::serialize_err(type_name::<Nested>(), "max depth exceeded")
+        );
+
+        assert!(to_vec_with_max_depth(&Nested(3), 3).is_ok());
+        assert!(to_vec_with_max_depth(&Nested(4), 3).is_err());
+        assert!(to_vec_with_max_depth(&Shape::Line(1, 2), 1).is_err());
+        assert!(to_vec_with_max_depth(&Ok::<Nested, ()>(Nested(1)), 1).is_err());
+        assert!(to_vec_with_max_depth(&Ok::<Nested, ()>(Nested(1)), 2).is_ok());
+    }
+
+    #[derive(Serialize)]
+    struct Unsorted {
+        zebra: u32,
+        apple: Inner,
+        #[serde(rename = "é")]
+        accent: bool,
+        mango: Vec<Inner>,
+    }
+
+    #[derive(Serialize)]
+    struct Inner {
+        b: u32,
+        a: u32,
+    }
+
+    #[test]
+    fn sorted_keys() {
+        let unsorted = Unsorted {
+            zebra: 1,
+            apple: Inner { b: 2, a: 3 },
+            accent: true,
+            mango: vec![Inner { b: 4, a: 5 }],
+        };
+
+        assert_eq!(
+            to_string(&unsorted).unwrap(),
+            r#"{"zebra":1,"apple":{"b":2,"a":3},"é":true,"mango":[{"b":4,"a":5}]}"#
+        );
+        assert_eq!(
+            String::from_utf8(to_vec_sorted(&unsorted).unwrap()).unwrap(),
+            r#"{"apple":{"a":3,"b":2},"mango":[{"a":5,"b":4}],"zebra":1,"é":true}"#
+        );
+
+        // Sorted structs sign the same bytes either way
+        assert_eq!(
+            to_vec_sorted(&params()).unwrap(),
+            to_vec(&params()).unwrap()
+        );
+        assert_eq!(
+            to_vec_sorted(&Shape::Rect {
+                width: 1,
+                height: 2
+            })
+            .unwrap(),
+            br#"{"rect":{"height":2,"width":1}}"#
+        );
+    }
+
+    #[test]
+    fn field_order() {
+        let inner = Inner { b: 2, a: 3 };
+        assert_eq!(
+            assert_sorted_fields(&inner).unwrap_err(),
+            StdError::serialize_err(
+                type_name::<Inner>(),
+                "Field a is out of order, it must come before b"
+            )
+        );
+        assert_eq!(
+            assert_sorted_fields(&vec![inner]).unwrap_err(),
+            StdError::serialize_err(
+                type_name::<Vec<Inner>>(),
+                "Field a is out of order, it must come before b"
+            )
+        );
+        assert!(assert_sorted_fields(&params()).is_ok());
+
+        // Numeric order, not alphabetical, maps are written sorted anyway
+        let mut numbers = BTreeMap::new();
+        numbers.insert(9u64, 1u32);
+        numbers.insert(10u64, 2u32);
+        assert!(assert_sorted_fields(&numbers).is_ok());
+        assert!(assert_sorted_fields(&vec![numbers]).is_ok());
+    }
+
+    #[test]
+    fn capacity() {
+        assert_eq!(
+            to_vec_with_capacity(&params(), 0).unwrap(),
+            to_vec(&params()).unwrap()
+        );
+        assert_eq!(to_vec_with_capacity(&(), 0).unwrap(), b"null");
+
+        let numbers: Vec<u64> = (0..10_000).collect();
+        let bytes = to_vec_with_capacity(&numbers, 0).unwrap();
+        assert_eq!(bytes, cosmwasm_std::to_vec(&numbers).unwrap());
+        // No more than one doubling past the output
+        assert!(bytes.capacity() < 2 * bytes.len());
+
+        let small = to_vec_with_capacity(&Point(1, 2), 8).unwrap();
+        assert_eq!(small, b"[1,2]");
+        assert_eq!(small.capacity(), 8);
+    }
+
+    #[test]
+    fn reused_buffer() {
+        let mut buf = Vec::with_capacity(4096);
+        buf.extend_from_slice(b"stale");
+        let ptr = buf.as_ptr();
+        for _ in 0..100 {
+            buf = to_vec_in(buf, &params()).unwrap();
+            assert_eq!(buf, to_vec(&params()).unwrap());
+        }
+        // Still the first allocation
+        assert_eq!(buf.capacity(), 4096);
+        assert_eq!(buf.as_ptr(), ptr);
+
+        // Outgrowing it keeps the grown capacity for the next call
+        let numbers: Vec<u64> = (0..10_000).collect();
+        buf = to_vec_in(buf, &numbers).unwrap();
+        let grown = buf.capacity();
+        assert!(grown > 4096);
+        buf = to_vec_in(buf, &params()).unwrap();
+        assert_eq!(buf.capacity(), grown);
+    }
+
+    /// Records the size of every write
+    struct Writes(Vec<u8>, Vec<usize>);
+
+    impl io::Write for Writes {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.extend_from_slice(buf);
+            self.1.push(buf.len());
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn writer() {
+        let mut out = vec![];
+        to_writer(&mut out, &params()).unwrap();
+        assert_eq!(out, to_vec(&params()).unwrap());
+
+        // Structs stream as they're written, a map is written once it's sorted
+        let mut map = BTreeMap::new();
+        map.insert("b".to_string(), vec![params()]);
+        map.insert("a".to_string(), vec![]);
+        let value = (params(), map);
+        let mut writes = Writes(vec![], vec![]);
+        to_writer(&mut writes, &value).unwrap();
+        assert_eq!(writes.0, to_vec(&value).unwrap());
+        // The largest write is the sorted map but its opening brace
+        let map_len = to_vec(&value.1).unwrap().len();
+        assert_eq!(writes.1.iter().max(), Some(&(map_len - 1)));
+
+        // Sink errors are reported
+        let mut full = [0u8; 8];
+        assert!(to_writer(&mut &mut full[..], &params()).is_err());
+    }
+
+    #[test]
+    fn long_arrays() {
+        // Far less stack than the default, enough for a flat array of any length
+        let serialized = std::thread::Builder::new()
+            .stack_size(64 * 1024)
+            .spawn(|| {
+                let numbers: Vec<u64> = (0..10_000).collect();
+                let strings: Vec<String> = numbers.iter().map(u64::to_string).collect();
+                (to_vec(&numbers).unwrap(), to_vec(&strings).unwrap())
+            })
+            .unwrap()
+            .join()
+            .unwrap();
+
+        let numbers: Vec<u64> = (0..10_000).collect();
+        assert_eq!(serialized.0, cosmwasm_std::to_vec(&numbers).unwrap());
+        assert!(serialized.1.ends_with(br#""9998","9999"]"#));
+    }
+
+    #[test]
+    fn trailing_newline() {
+        let point = Point(1, 2);
+        assert_eq!(to_string(&point).unwrap(), "[1,2]");
+        assert_eq!(to_string_with_trailing_newline(&point).unwrap(), "[1,2]\n");
+
+        let plain = to_vec(&point).unwrap();
+        let with_newline = to_vec_with_trailing_newline(&point).unwrap();
+        assert_eq!(
+            eth_signed_message(&plain),
+            b"\x19Ethereum Signed Message:\n5[1,2]"
+        );
+        assert_eq!(
+            eth_signed_message(&with_newline),
+            b"\x19Ethereum Signed Message:\n6[1,2]\n"
+        );
+    }
+
+    #[derive(Serialize)]
+    struct ControlKey {
+        #[serde(rename = "a\nb")]
+        field: u32,
+    }
+
+    #[test]
+    fn strict_keys() {
+        let mut map = BTreeMap::new();
+        map.insert("a\u{1}".to_string(), 1u32);
+        assert_eq!(to_string(&map).unwrap(), r#"{"a\u0001":1}"#);
+        assert_eq!(
+            to_vec_strict(&map).unwrap_err(),
+            StdError::serialize_err(
+                type_name::<BTreeMap<String, u32>>(),
+                r#"Key "a\u{1}" contains a control character"#
+            )
+        );
+
+        assert!(to_vec(&ControlKey { field: 1 }).is_ok());
+        assert!(to_vec_strict(&ControlKey { field: 1 }).is_err());
+
+        // Control characters in values are still escaped
+        assert_eq!(
+            to_vec_strict(&params()).unwrap(),
+            to_vec(&params()).unwrap()
+        );
+    }
+
+    #[test]
+    fn forward_slashes() {
+        let memo = "a/b";
+        assert_eq!(to_string(&memo).unwrap(), r#""a/b""#);
+        assert_eq!(to_vec_escaping_slashes(&memo).unwrap(), br#""a\/b""#);
+
+        let mut map = BTreeMap::new();
+        map.insert("denom/path".to_string(), "ibc/27394FB0".to_string());
+        assert_eq!(
+            to_vec_escaping_slashes(&map).unwrap(),
+            br#"{"denom\/path":"ibc\/27394FB0"}"#
+        );
+
+        // Both forms parse back to the same string
+        let escaped: String = from_slice(&to_vec_escaping_slashes(&memo).unwrap()).unwrap();
+        assert_eq!(escaped, memo);
+    }
+
+    #[test]
+    fn unsupported_types() {
+        assert!(to_vec(&f64::NAN).is_err());
+        assert!(to_vec(&f32::INFINITY).is_err());
+        assert!(to_vec(&1u128).is_err());
+    }
+
+    #[test]
+    fn floats() {
+        // Shortest digits that round-trip, not the exact binary value
+        assert_eq!(to_string(&0.1f64).unwrap(), "1.0E-1");
+        assert_eq!(to_string(&0.1f32).unwrap(), "1.0E-1");
+        assert_eq!(to_string(&1.5f64).unwrap(), "1.5E0");
+        assert_eq!(to_string(&-123.456f64).unwrap(), "-1.23456E2");
+        assert_eq!(to_string(&1e-7f64).unwrap(), "1.0E-7");
+        assert_eq!(
+            to_string(&f64::MIN_POSITIVE).unwrap(),
+            "2.2250738585072014E-308"
+        );
+
+        // Integers never take an exponent, however large
+        assert_eq!(
+            to_string(&1e100f64).unwrap(),
+            format!("1{}", "0".repeat(100))
+        );
+        assert_eq!(to_string(&-0.0f64).unwrap(), "0");
+
+        for v in [0.1, 1.5, -123.456, 1e-7, 1e100, f64::MAX, f64::MIN_POSITIVE] {
+            assert_eq!(to_string(&v).unwrap().parse::<f64>().unwrap(), v);
+        }
+        assert_eq!(
+            to_string(&f64::NAN).unwrap_err(),
+            StdError::serialize_err("f64", "NaN is not a JSON number")
+        );
+    }
+
+    #[test]
+    fn float_normalization() {
+        // Both zeros sign the same bytes
+        assert_eq!(to_vec(&-0.0f64).unwrap(), to_vec(&0.0f64).unwrap());
+        assert_eq!(to_vec(&-0.0f32).unwrap(), to_vec(&0.0f32).unwrap());
+        assert_eq!(to_string(&0.0f64).unwrap(), "0");
+
+        // Integer valued floats are written as integers
+        assert_eq!(to_string(&2.0f64).unwrap(), "2");
+        assert_eq!(to_string(&-2.0f64).unwrap(), "-2");
+        assert_eq!(to_string(&2.0f32).unwrap(), "2");
+        assert_eq!(to_string(&2.0f64).unwrap(), to_string(&2u64).unwrap());
+        assert_eq!(to_string(&1e21f64).unwrap(), "1000000000000000000000");
+        assert_eq!(to_string(&2.5f64).unwrap(), "2.5E0");
+
+        let mut map = BTreeMap::new();
+        map.insert("negative", -0.0f64);
+        map.insert("positive", 0.0f64);
+        assert_eq!(to_string(&map).unwrap(), r#"{"negative":0,"positive":0}"#);
+    }
+
+    #[remain::sorted]
+    #[derive(Serialize)]
+    struct Flattened {
+        address: String,
+        #[serde(flatten)]
+        extra: BTreeMap<String, u32>,
+    }
+
+    #[test]
+    fn maps() {
+        let mut strings = BTreeMap::new();
+        strings.insert("balance".to_string(), 1u32);
+        strings.insert("quote\"".to_string(), 2u32);
+        assert_eq!(to_string(&strings).unwrap(), r#"{"balance":1,"quote\"":2}"#);
+
+        let mut numbers = BTreeMap::new();
+        numbers.insert(1u64, 10u32);
+        numbers.insert(u64::MAX, 20u32);
+        assert_eq!(
+            to_string(&numbers).unwrap(),
+            r#"{"1":10,"18446744073709551615":20}"#
+        );
+        assert_eq!(to_string(&BTreeMap::<u64, u32>::new()).unwrap(), "{}");
+
+        assert_eq!(
+            to_string(&Flattened {
+                address: "secret1".to_string(),
+                extra: strings,
+            })
+            .unwrap(),
+            r#"{"address":"secret1","balance":1,"quote\"":2}"#
+        );
+
+        assert_eq!(
+            to_string(&numbers).unwrap(),
+            String::from_utf8(to_vec_sorted(&numbers).unwrap()).unwrap()
+        );
+        // Integer keys are sorted as strings, even without sorting struct members
+        numbers.insert(9, 30);
+        assert_eq!(
+            to_vec(&numbers).unwrap(),
+            br#"{"1":10,"18446744073709551615":20,"9":30}"#
+        );
+        // serde_json keeps the numeric order of the map
+        assert_eq!(
+            serde_json::to_vec(&numbers).unwrap(),
+            br#"{"1":10,"9":30,"18446744073709551615":20}"#
+        );
+        assert_eq!(
+            to_vec_sorted(&numbers).unwrap(),
+            br#"{"1":10,"18446744073709551615":20,"9":30}"#
+        );
+
+        let mut tuples = BTreeMap::new();
+        tuples.insert((1u8, 2u8), 3u32);
+        assert_eq!(
+            to_vec(&tuples).unwrap_err(),
+            StdError::serialize_err(
+                type_name::<BTreeMap<(u8, u8), u32>>(),
+                "Map keys must be strings, chars or integers"
+            )
+        );
+    }
+
+    #[test]
+    fn canonical_raw_params() {
+        let json = r#"{"a":[1,-2,true,null,"x\n\u001F"],"b":{},"c":18446744073709551615}"#;
+        assert_eq!(RawParams::new(json.as_bytes()).unwrap().as_str(), json);
+
+        for json in [
+            r#"{"a": 1}"#,
+            r#"{"b":1,"a":2}"#,
+            r#"{"a":1.5}"#,
+            r#"{"a":1e3}"#,
+            "18446744073709551616",
+            "-9223372036854775809",
+            "-0",
+            "01",
+            r#""\u0041""#,
+            r#""\/""#,
+            r#""\uD83D\uDE00""#,
+            "\"\n\"",
+            "nul",
+            "[1,]",
+            "{\"a\"}",
+            "",
+        ]
+        .iter()
+        {
+            assert!(RawParams::new(json.as_bytes()).is_err(), "{}", json);
+        }
+
+        assert_eq!(
+            RawParams::new(br#"{"a":1,"a":2}"#).unwrap_err(),
+            StdError::parse_err("RawParams", "Duplicate key \"a\"")
+        );
+        assert_eq!(
+            RawParams::new(br#"{"a":1.5}"#).unwrap_err(),
+            StdError::parse_err("RawParams", "f64 is not supported in a sign-doc")
+        );
+
+        let nested = |depth| format!("{}{}", "[".repeat(depth), "]".repeat(depth));
+        assert!(RawParams::new(nested(DEFAULT_MAX_DEPTH).as_bytes()).is_ok());
+        assert_eq!(
+            RawParams::new(nested(DEFAULT_MAX_DEPTH + 1).as_bytes()).unwrap_err(),
+            StdError::parse_err("RawParams", "max depth exceeded")
+        );
+    }
+}
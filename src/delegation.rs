@@ -0,0 +1,116 @@
+use crate::permit::{try_bech32_to_canonical, Permit};
+use cosmwasm_std::{Api, CanonicalAddr, HumanAddr, StdError, StdResult};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Signed by the granter to let the grantee act on its behalf
+#[remain::sorted]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct DelegationParams {
+    pub grantee: HumanAddr,
+    pub granter: HumanAddr,
+    pub scopes: Vec<String>,
+}
+
+pub type DelegationPermit = Permit<DelegationParams>;
+
+/// Validated delegation, contracts must still check that the caller is the grantee
+#[derive(Clone, Debug, PartialEq)]
+pub struct Delegation {
+    pub grantee: CanonicalAddr,
+    pub granter: CanonicalAddr,
+    pub scopes: Vec<String>,
+}
+
+impl Permit<DelegationParams> {
+    /// Validates the permit and confirms that it was signed by the granter
+    pub fn validate_delegation<A: Api>(
+        &self,
+        api: &A,
+        msg_type: Option<String>,
+    ) -> StdResult<Delegation> {
+        let signer = self.validate(api, msg_type)?;
+        let granter = try_bech32_to_canonical(self.params.granter.as_str())?;
+
+        if signer.as_signer_canonical()? != granter {
+            return Err(StdError::generic_err(
+                "Delegation permit was not signed by the granter",
+            ));
+        }
+
+        Ok(Delegation {
+            grantee: try_bech32_to_canonical(self.params.grantee.as_str())?,
+            granter,
+            scopes: self.params.scopes.clone(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod delegation_tests {
+    use super::*;
+    use crate::permit::bech32_to_canonical;
+    use crate::transaction::{PermitSignature, PubKey};
+    use cosmwasm_std::testing::mock_dependencies;
+    use cosmwasm_std::Binary;
+
+    const SIGNER: &str = "secret1wxyx7cgz8pjcnzt0skqwaup8043sxnd7t7u8d8";
+    const PUBKEY: &str = "AimifPuikvLOiLaYvw+By4mBXcPsUVKr/7YWP1I6irWG";
+    const OTHER: &str = "secret1w508d6qejxtdg4y5r3zarvary0c5xw7kccrnjy";
+
+    fn permit(granter: &str, grantee: &str, signature: &str) -> DelegationPermit {
+        Permit {
+            params: DelegationParams {
+                grantee: HumanAddr(grantee.to_string()),
+                granter: HumanAddr(granter.to_string()),
+                scopes: vec!["balance".to_string()],
+            },
+            chain_id: None,
+            sequence: None,
+            signature: PermitSignature {
+                pub_key: PubKey::new(Binary::from_base64(PUBKEY).unwrap()),
+                signature: Binary::from_base64(signature).unwrap(),
+            },
+            account_number: None,
+            memo: None,
+            expires: None,
+            code_hash: None,
+            action: None,
+            fee: None,
+        }
+    }
+
+    #[test]
+    fn granter_signature() {
+        let deps = mock_dependencies(20, &[]);
+
+        let delegation = permit(
+            SIGNER,
+            OTHER,
+            "buvmMQsgEZV/Ol76VGFEjkXpqqzgwkvfs7q8SyDV/dIq+jSnlh1bDUSGtlOIdxwc5502lGVpuJQEjbzh8zmedg==",
+        )
+        .validate_delegation(&deps.api, None)
+        .unwrap();
+        assert_eq!(
+            delegation,
+            Delegation {
+                grantee: bech32_to_canonical(OTHER),
+                granter: bech32_to_canonical(SIGNER),
+                scopes: vec!["balance".to_string()],
+            }
+        );
+
+        // Valid signature, but the signer is the grantee instead
+        assert_eq!(
+            permit(
+                OTHER,
+                SIGNER,
+                "24T6NCzQhDvdnZiDk97YtiwJX6xwzasJ5Lrp7cMol8QMb6Wr5erfFOdSlSNcEyoSIyHcBZq+jwmPbO1nPNXWMw==",
+            )
+            .validate_delegation(&deps.api, None)
+            .unwrap_err(),
+            StdError::generic_err("Delegation permit was not signed by the granter")
+        );
+    }
+}
@@ -0,0 +1,366 @@
+use crate::compact::Error;
+use cosmwasm_std::{Binary, StdError, StdResult};
+use serde::{ser, Serialize};
+
+/// Flattens a value into (path, value) pairs, one for each leaf.
+/// Paths are joined with dots and sequence items are addressed by their index,
+/// empty sequences and objects are kept as "[]" and "{}" so nothing signed is hidden.
+pub(crate) fn flatten<T: Serialize + ?Sized>(value: &T) -> StdResult<Vec<(String, String)>> {
+    let mut collector = FieldCollector {
+        path: vec![],
+        fields: vec![],
+    };
+    value
+        .serialize(&mut collector)
+        .map_err(|err| StdError::generic_err(err.0))?;
+    Ok(collector.fields)
+}
+
+struct FieldCollector {
+    path: Vec<String>,
+    fields: Vec<(String, String)>,
+}
+
+impl FieldCollector {
+    fn leaf(&mut self, value: String) -> Result<(), Error> {
+        self.fields.push((self.path.join("."), value));
+        Ok(())
+    }
+
+    fn nested<T: Serialize + ?Sized>(&mut self, key: String, value: &T) -> Result<(), Error> {
+        self.path.push(key);
+        value.serialize(&mut *self)?;
+        self.path.pop();
+        Ok(())
+    }
+}
+
+struct Compound<'a> {
+    collector: &'a mut FieldCollector,
+    /// Amount of items already seen
+    len: usize,
+    /// Pushed variant name that has to be popped at the end
+    variant: bool,
+    empty: &'static str,
+    key: Option<String>,
+}
+
+impl<'a> Compound<'a> {
+    fn new(collector: &'a mut FieldCollector, empty: &'static str) -> Self {
+        Self {
+            collector,
+            len: 0,
+            variant: false,
+            empty,
+            key: None,
+        }
+    }
+
+    fn variant(collector: &'a mut FieldCollector, variant: &str, empty: &'static str) -> Self {
+        collector.path.push(variant.to_string());
+        Self {
+            variant: true,
+            ..Self::new(collector, empty)
+        }
+    }
+
+    fn item<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.collector.nested(self.len.to_string(), value)?;
+        self.len += 1;
+        Ok(())
+    }
+
+    fn field<T: Serialize + ?Sized>(&mut self, key: String, value: &T) -> Result<(), Error> {
+        self.collector.nested(key, value)?;
+        self.len += 1;
+        Ok(())
+    }
+
+    fn finish(self) -> Result<(), Error> {
+        if self.len == 0 {
+            self.collector.leaf(self.empty.to_string())?;
+        }
+        if self.variant {
+            self.collector.path.pop();
+        }
+        Ok(())
+    }
+}
+
+impl<'a> ser::Serializer for &'a mut FieldCollector {
+    type Ok = ();
+    type Error = Error;
+    type SerializeSeq = Compound<'a>;
+    type SerializeTuple = Compound<'a>;
+    type SerializeTupleStruct = Compound<'a>;
+    type SerializeTupleVariant = Compound<'a>;
+    type SerializeMap = Compound<'a>;
+    type SerializeStruct = Compound<'a>;
+    type SerializeStructVariant = Compound<'a>;
+
+    fn serialize_bool(self, v: bool) -> Result<(), Error> {
+        self.leaf(v.to_string())
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<(), Error> {
+        self.leaf(v.to_string())
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<(), Error> {
+        self.leaf(v.to_string())
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<(), Error> {
+        self.leaf(v.to_string())
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<(), Error> {
+        self.leaf(v.to_string())
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<(), Error> {
+        self.leaf(v.to_string())
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<(), Error> {
+        self.leaf(v.to_string())
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<(), Error> {
+        self.leaf(v.to_string())
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<(), Error> {
+        self.leaf(v.to_string())
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<(), Error> {
+        self.leaf(v.to_string())
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<(), Error> {
+        self.leaf(v.to_string())
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<(), Error> {
+        self.leaf(v.to_string())
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<(), Error> {
+        self.leaf(v.to_string())
+    }
+
+    fn serialize_char(self, v: char) -> Result<(), Error> {
+        self.leaf(v.to_string())
+    }
+
+    fn serialize_str(self, v: &str) -> Result<(), Error> {
+        self.leaf(v.to_string())
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<(), Error> {
+        self.leaf(Binary::from(v).to_base64())
+    }
+
+    fn serialize_none(self) -> Result<(), Error> {
+        self.leaf("null".to_string())
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<(), Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<(), Error> {
+        self.leaf("null".to_string())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<(), Error> {
+        self.serialize_unit()
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<(), Error> {
+        self.leaf(variant.to_string())
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        self.nested(variant.to_string(), value)
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Compound<'a>, Error> {
+        Ok(Compound::new(self, "[]"))
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Compound<'a>, Error> {
+        Ok(Compound::new(self, "[]"))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Compound<'a>, Error> {
+        Ok(Compound::new(self, "[]"))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Compound<'a>, Error> {
+        Ok(Compound::variant(self, variant, "[]"))
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Compound<'a>, Error> {
+        Ok(Compound::new(self, "{}"))
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Compound<'a>, Error> {
+        Ok(Compound::new(self, "{}"))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Compound<'a>, Error> {
+        Ok(Compound::variant(self, variant, "{}"))
+    }
+}
+
+impl<'a> ser::SerializeSeq for Compound<'a> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.item(value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.finish()
+    }
+}
+
+impl<'a> ser::SerializeTuple for Compound<'a> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.item(value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.finish()
+    }
+}
+
+impl<'a> ser::SerializeTupleStruct for Compound<'a> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.item(value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.finish()
+    }
+}
+
+impl<'a> ser::SerializeTupleVariant for Compound<'a> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.item(value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.finish()
+    }
+}
+
+impl<'a> ser::SerializeMap for Compound<'a> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), Error> {
+        // Keys are flattened on their own and must end up as a single leaf
+        let mut fields = flatten(key).map_err(|err| Error(err.to_string()))?;
+        match (fields.pop(), fields.is_empty()) {
+            (Some((path, key)), true) if path.is_empty() => {
+                self.key = Some(key);
+                Ok(())
+            }
+            _ => Err(Error("Map keys must be plain values".to_string())),
+        }
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        let key = self
+            .key
+            .take()
+            .ok_or_else(|| Error("Map value without a key".to_string()))?;
+        self.field(key, value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.finish()
+    }
+}
+
+impl<'a> ser::SerializeStruct for Compound<'a> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        self.field(key.to_string(), value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.finish()
+    }
+}
+
+impl<'a> ser::SerializeStructVariant for Compound<'a> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        self.field(key.to_string(), value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.finish()
+    }
+}
@@ -1,9 +1,29 @@
+pub mod auth;
+pub mod compact;
+pub mod delegation;
+mod fields;
+mod macros;
 pub mod permit;
+pub mod revocation;
+mod size;
 pub mod transaction;
 pub mod viewing_keys;
 
+pub use compact::assert_sorted_fields;
+pub use size::estimate_serialized_size;
+
 use sha2::{Digest, Sha256};
 
+// Used by permit_params!
+#[doc(hidden)]
+pub use macros::FieldType;
+#[doc(hidden)]
+pub use remain;
+#[doc(hidden)]
+pub use schemars;
+#[doc(hidden)]
+pub use serde;
+
 pub const SHA256_HASH_SIZE: usize = 32;
 
 pub fn sha_256(data: &[u8]) -> [u8; SHA256_HASH_SIZE] {
@@ -0,0 +1,179 @@
+/// Declares a permit params struct with the annotations signatures depend on.
+/// Fields still have to be written in alphabetical order, remain rejects them otherwise.
+#[macro_export]
+macro_rules! permit_params {
+    (
+        $(#[$meta:meta])*
+        $vis:vis struct $name:ident {
+            $($(#[$field_meta:meta])* $field_vis:vis $field:ident: $ty:ty),* $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        #[$crate::remain::sorted]
+        #[derive(
+            $crate::serde::Serialize,
+            $crate::serde::Deserialize,
+            Clone,
+            Debug,
+            PartialEq,
+            $crate::schemars::JsonSchema
+        )]
+        #[serde(rename_all = "snake_case")]
+        $vis struct $name {
+            // Written as a projection so the derives see a span from this macro,
+            // the JsonSchema derive can't resolve its locals next to the caller's types otherwise
+            $($(#[$field_meta])* $field_vis $field: <$ty as $crate::FieldType>::Type),*
+        }
+    };
+}
+
+/// Identity projection used by permit_params! fields
+#[doc(hidden)]
+pub trait FieldType {
+    type Type;
+}
+
+impl<T> FieldType for T {
+    type Type = T;
+}
+
+/// Authenticates an Auth and runs the query body with the authenticated CanonicalAddr,
+/// authentication errors are returned before the body runs.
+///
+/// `authenticate_query!(api, auth, msg_type, stored_hash, ViewingKeyType, KEY_SIZE, |addr| body)`
+#[macro_export]
+macro_rules! authenticate_query {
+    (
+        $api:expr, $auth:expr, $msg_type:expr, $stored_hash:expr, $key:ty, $key_size:expr,
+        |$addr:ident| $body:expr
+    ) => {
+        match $auth.authenticate::<_, $key, _, $key_size>($api, $msg_type, $stored_hash) {
+            Ok($addr) => $body,
+            Err(err) => Err(err),
+        }
+    };
+}
+
+#[cfg(test)]
+mod macro_tests {
+    use crate::auth::Auth;
+    use crate::permit::{bech32_to_canonical, PermitBuilder};
+    use crate::transaction::PermitSignature;
+    use crate::viewing_keys::ViewingKey;
+    use cosmwasm_std::testing::mock_dependencies;
+    use cosmwasm_std::{
+        to_binary, to_vec, Binary, CanonicalAddr, HumanAddr, StdError, StdResult, Uint128,
+    };
+
+    crate::permit_params! {
+        pub struct MacroPermitMsg {
+            pub address: String,
+            pub some_number: Uint128,
+        }
+    }
+
+    const ADDRESS: &str = "secret102nasmxnxvwp5agc4lp3flc6s23335xm8g7gn9";
+    const PUBKEY: &str = "A0qzJ3s16OKUfn1KFyh533vBnBOQIT0jm+R/FBobJCfa";
+    const SIGNED_TX: &str =
+        "4pZtghyHKHHmwiGNC5JD8JxCJiO+44j6GqaLPc19Q7lt85tr0IRZHYcnc0pkokIds8otxU9rcuvPXb0+etLyVA==";
+
+    #[test]
+    fn macro_params() {
+        let params = MacroPermitMsg {
+            address: ADDRESS.to_string(),
+            some_number: Uint128(10),
+        };
+
+        assert_eq!(
+            String::from_utf8(to_vec(&params).unwrap()).unwrap(),
+            format!("{{\"address\":\"{}\",\"some_number\":\"10\"}}", ADDRESS)
+        );
+
+        let permit = PermitBuilder::new()
+            .params(params)
+            .chain_id("pulsar-1")
+            .signature(PermitSignature::from_base64(PUBKEY, SIGNED_TX).unwrap())
+            .build()
+            .unwrap();
+
+        let deps = mock_dependencies(20, &[]);
+        let addr = permit.validate(&deps.api, None).unwrap();
+        assert_eq!(addr.as_canonical().unwrap(), bech32_to_canonical(ADDRESS));
+    }
+
+    struct Key(String);
+
+    impl std::fmt::Display for Key {
+        fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+            f.write_str(&self.0)
+        }
+    }
+
+    impl From<String> for Key {
+        fn from(key: String) -> Self {
+            Key(key)
+        }
+    }
+
+    impl ViewingKey<32> for Key {}
+
+    enum QueryMsg {
+        Balance { auth: Auth<MacroPermitMsg> },
+    }
+
+    fn stored_hash(addr: &CanonicalAddr) -> StdResult<Option<Vec<u8>>> {
+        if *addr == bech32_to_canonical(ADDRESS) {
+            Ok(Some(Key("password".to_string()).hash().to_vec()))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn query(msg: QueryMsg) -> StdResult<Binary> {
+        let deps = mock_dependencies(20, &[]);
+        match msg {
+            QueryMsg::Balance { auth } => {
+                crate::authenticate_query!(&deps.api, auth, None, stored_hash, Key, 32, |addr| {
+                    to_binary(&Uint128(addr.len() as u128))
+                })
+            }
+        }
+    }
+
+    #[test]
+    fn authenticated_query() {
+        let permit = Auth::Permit(
+            PermitBuilder::new()
+                .params(MacroPermitMsg {
+                    address: ADDRESS.to_string(),
+                    some_number: Uint128(10),
+                })
+                .chain_id("pulsar-1")
+                .signature(PermitSignature::from_base64(PUBKEY, SIGNED_TX).unwrap())
+                .build()
+                .unwrap(),
+        );
+        assert_eq!(
+            query(QueryMsg::Balance { auth: permit }).unwrap(),
+            to_binary(&Uint128(20)).unwrap()
+        );
+
+        let viewing_key = Auth::ViewingKey {
+            address: HumanAddr(ADDRESS.to_string()),
+            key: "password".to_string(),
+        };
+        assert_eq!(
+            query(QueryMsg::Balance { auth: viewing_key }).unwrap(),
+            to_binary(&Uint128(20)).unwrap()
+        );
+
+        let wrong_key = Auth::ViewingKey {
+            address: HumanAddr(ADDRESS.to_string()),
+            key: "wrong_password".to_string(),
+        };
+        assert_eq!(
+            query(QueryMsg::Balance { auth: wrong_key }).unwrap_err(),
+            StdError::generic_err("Wrong viewing key for this address or viewing key not set")
+        );
+    }
+}
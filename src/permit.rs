@@ -1,9 +1,18 @@
-use crate::sha_256;
-use crate::transaction::{PermitSignature, PubKeyValue, SignedTx};
-use bech32::FromBase32;
-use cosmwasm_std::{to_binary, Api, Binary, CanonicalAddr, StdError, StdResult, Uint128};
+use crate::compact;
+use crate::fields::flatten;
+use crate::transaction::{
+    Fee, PermitSignature, PubKeyValue, SigScheme, SignedTx, SignedTxRef, SigningPayload,
+};
+use crate::{sha_256, SHA256_HASH_SIZE};
+use bech32::{FromBase32, ToBase32, Variant};
+use cosmwasm_std::{
+    from_slice, Api, Binary, CanonicalAddr, HumanAddr, StdError, StdResult, Uint128,
+};
 use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fmt;
 
 // NOTE: Struct order is very important for signatures
 
@@ -12,13 +21,333 @@ use serde::{Deserialize, Serialize};
 /// Where the information will be stored
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
-pub struct Permit<T: Clone + Serialize> {
+pub struct Permit<T: Serialize> {
     pub params: T,
     pub signature: PermitSignature,
     pub account_number: Option<Uint128>,
     pub chain_id: Option<String>,
     pub sequence: Option<Uint128>,
     pub memo: Option<String>,
+    /// Block height or timestamp after which the permit is rejected, only signed when set
+    pub expires: Option<u64>,
+    /// Code hash of the contract the permit is meant for, only signed when set
+    pub code_hash: Option<String>,
+    /// Single query or action the permit was signed for, only signed when set
+    pub action: Option<String>,
+    /// Fee the wallet put in the sign-doc, Fee::default() when unset
+    pub fee: Option<Fee>,
+}
+
+/// Builds a permit field by field, unset optional fields stay None so the sign-doc
+/// falls back to the same defaults as SignedTx::from_permit
+#[derive(Clone, Debug)]
+pub struct PermitBuilder<T: Serialize> {
+    params: Option<T>,
+    signature: Option<PermitSignature>,
+    account_number: Option<Uint128>,
+    chain_id: Option<String>,
+    sequence: Option<Uint128>,
+    memo: Option<String>,
+    expires: Option<u64>,
+    code_hash: Option<String>,
+    action: Option<String>,
+    fee: Option<Fee>,
+}
+
+impl<T: Serialize> Default for PermitBuilder<T> {
+    fn default() -> Self {
+        Self {
+            params: None,
+            signature: None,
+            account_number: None,
+            chain_id: None,
+            sequence: None,
+            memo: None,
+            expires: None,
+            code_hash: None,
+            action: None,
+            fee: None,
+        }
+    }
+}
+
+impl<T: Serialize> PermitBuilder<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn params(mut self, params: T) -> Self {
+        self.params = Some(params);
+        self
+    }
+
+    pub fn signature(mut self, signature: PermitSignature) -> Self {
+        self.signature = Some(signature);
+        self
+    }
+
+    pub fn account_number(mut self, account_number: Uint128) -> Self {
+        self.account_number = Some(account_number);
+        self
+    }
+
+    pub fn chain_id(mut self, chain_id: &str) -> Self {
+        self.chain_id = Some(chain_id.to_string());
+        self
+    }
+
+    pub fn sequence(mut self, sequence: Uint128) -> Self {
+        self.sequence = Some(sequence);
+        self
+    }
+
+    pub fn memo(mut self, memo: &str) -> Self {
+        self.memo = Some(memo.to_string());
+        self
+    }
+
+    pub fn expires(mut self, expires: u64) -> Self {
+        self.expires = Some(expires);
+        self
+    }
+
+    pub fn code_hash(mut self, code_hash: &str) -> Self {
+        self.code_hash = Some(code_hash.to_string());
+        self
+    }
+
+    pub fn action(mut self, action: &str) -> Self {
+        self.action = Some(action.to_string());
+        self
+    }
+
+    pub fn fee(mut self, fee: Fee) -> Self {
+        self.fee = Some(fee);
+        self
+    }
+
+    /// Fails if the params or the signature were never set
+    pub fn build(self) -> StdResult<Permit<T>> {
+        Ok(Permit {
+            params: self
+                .params
+                .ok_or_else(|| StdError::generic_err("Permit params are missing"))?,
+            signature: self
+                .signature
+                .ok_or_else(|| StdError::generic_err("Permit signature is missing"))?,
+            account_number: self.account_number,
+            chain_id: self.chain_id,
+            sequence: self.sequence,
+            memo: self.memo,
+            expires: self.expires,
+            code_hash: self.code_hash,
+            action: self.action,
+            fee: self.fee,
+        })
+    }
+}
+
+/// Why a permit failed validation, converts into StdError so ? keeps working in StdResult code
+#[derive(Debug, PartialEq)]
+pub enum PermitError {
+    /// Pubkey type isn't one of the SigScheme variants
+    UnsupportedScheme(String),
+    /// Pubkey bytes aren't a valid secp256k1 key
+    InvalidPubKey(String),
+    /// The verifier errored, i.e. on a malformed signature
+    VerifyFailed(String),
+    /// The verifier recovered a key that is neither a compressed nor an uncompressed
+    /// secp256k1 key, holds its length
+    InvalidRecoveredKey(usize),
+    /// The signature doesn't match the sign-doc
+    SignatureMismatch,
+    Expired,
+    /// The permit doesn't name the chain the contract runs on
+    ChainIdMismatch(String),
+    /// The permit sequence isn't past the last seen one
+    StaleSequence(Uint128),
+    /// The signed message at this index isn't the expected one
+    MessageMismatch(usize),
+    /// The permit wasn't signed for this action
+    ActionMismatch(String),
+    CodeHashMismatch,
+    /// Strict validation needs the chain id to be signed
+    MissingChainId,
+    /// The signed chain id isn't one of the allowed ones
+    ChainIdNotAllowed(String),
+    AccountNumberOutOfRange {
+        account_number: Uint128,
+        min: Uint128,
+        max: Uint128,
+    },
+    /// The params were signed with another layout version
+    UnsupportedVersion {
+        version: u32,
+        expected: u32,
+    },
+    Std(StdError),
+}
+
+impl fmt::Display for PermitError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PermitError::UnsupportedScheme(pubkey_type) => {
+                let supported: Vec<&str> = SigScheme::ALL
+                    .iter()
+                    .map(|scheme| scheme.pubkey_type())
+                    .collect();
+                write!(
+                    f,
+                    "Unsupported signature scheme {}, supported schemes: {}",
+                    pubkey_type,
+                    supported.join(", ")
+                )
+            }
+            PermitError::InvalidPubKey(err) => write!(f, "Invalid pubkey: {}", err),
+            PermitError::VerifyFailed(err) => write!(f, "Signature verification errored: {}", err),
+            PermitError::InvalidRecoveredKey(len) => write!(
+                f,
+                "Recovered pubkey is {} bytes long, expected 33 or 65",
+                len
+            ),
+            PermitError::SignatureMismatch => f.write_str("Signature verification failed"),
+            PermitError::Expired => f.write_str("Permit expired"),
+            PermitError::ChainIdMismatch(expected) => {
+                write!(f, "Permit was not signed for chain id {}", expected)
+            }
+            PermitError::StaleSequence(last_seen) => {
+                write!(f, "Permit sequence must be greater than {}", last_seen)
+            }
+            PermitError::MessageMismatch(index) => {
+                write!(f, "Permit message {} is not the expected one", index)
+            }
+            PermitError::ActionMismatch(expected) => {
+                write!(f, "Permit was not signed for action {}", expected)
+            }
+            PermitError::CodeHashMismatch => {
+                f.write_str("Permit was not signed for this contract code hash")
+            }
+            PermitError::MissingChainId => f.write_str("Permit must specify a chain id"),
+            PermitError::ChainIdNotAllowed(chain_id) => {
+                write!(f, "Chain id {} is not allowed", chain_id)
+            }
+            PermitError::AccountNumberOutOfRange {
+                account_number,
+                min,
+                max,
+            } => write!(
+                f,
+                "Account number {} is outside of {}..={}",
+                account_number, min, max
+            ),
+            PermitError::UnsupportedVersion { version, expected } => write!(
+                f,
+                "Permit params version {} is not supported, expected {}",
+                version, expected
+            ),
+            PermitError::Std(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl From<StdError> for PermitError {
+    fn from(err: StdError) -> Self {
+        PermitError::Std(err)
+    }
+}
+
+impl From<PermitError> for StdError {
+    fn from(err: PermitError) -> Self {
+        match err {
+            PermitError::Std(err) => err,
+            err => StdError::generic_err(err.to_string()),
+        }
+    }
+}
+
+/// Everything known about a validated permit
+#[derive(Clone, Debug, PartialEq)]
+pub struct AuthContext {
+    /// Signer address using the signed chain's prefix
+    pub address: HumanAddr,
+    pub canonical: CanonicalAddr,
+    pub chain_id: String,
+    pub permit_key: String,
+    /// Pubkey type the signature was verified with
+    pub scheme: String,
+    pub sequence: Uint128,
+}
+
+/// Exact bytes each scheme hashes before verifying, lets frontends diff their own sign-doc
+#[derive(Clone, Debug, PartialEq)]
+pub struct Preimages {
+    /// SHA-256 input of tendermint secp256k1 and ed25519 keys
+    pub cosmos_sha256_input: Vec<u8>,
+    /// Keccak256 input of Ethereum wallets, the sign-doc itself for ethermint keys and the
+    /// EIP-191 wrapped sign-doc when the pubkey is left out to be recovered
+    pub eth_keccak_input: Vec<u8>,
+}
+
+/// Params carrying a version discriminator, lets a contract refuse permits
+/// signed for an older params layout
+pub trait ParamsVersion {
+    /// The only version this params type accepts
+    const VERSION: u32;
+
+    fn version(&self) -> u32;
+}
+
+/// Remembers which (pubkey, sign-doc hash) pairs were already verified.
+/// Must only live for a single execution, never store it.
+#[derive(Default)]
+pub struct ValidationCache {
+    verified: HashSet<(Vec<u8>, [u8; SHA256_HASH_SIZE])>,
+    hits: u64,
+}
+
+impl ValidationCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Amount of validations that skipped signature verification
+    pub fn hits(&self) -> u64 {
+        self.hits
+    }
+
+    pub fn len(&self) -> usize {
+        self.verified.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.verified.is_empty()
+    }
+}
+
+/// Bech32 prefix used by the chain, based on the chain id without its revision number
+pub fn prefix_for_chain_id(chain_id: &str) -> Option<&'static str> {
+    let name = match chain_id.rsplit_once('-') {
+        Some((name, revision)) if revision.chars().all(|c| c.is_ascii_digit()) => name,
+        _ => chain_id,
+    };
+
+    match name {
+        "secret" | "pulsar" => Some("secret"),
+        "columbus" | "bombay" | "phoenix" | "pisco" => Some("terra"),
+        "osmosis" => Some("osmo"),
+        "injective" => Some("inj"),
+        "juno" | "uni" => Some("juno"),
+        "cosmoshub" => Some("cosmos"),
+        _ => None,
+    }
+}
+
+pub(crate) fn try_bech32_to_canonical(addr: &str) -> StdResult<CanonicalAddr> {
+    let (_, data, _) =
+        bech32::decode(addr).map_err(|err| StdError::generic_err(err.to_string()))?;
+    let bytes =
+        Vec::<u8>::from_base32(&data).map_err(|err| StdError::generic_err(err.to_string()))?;
+    Ok(CanonicalAddr(Binary(bytes)))
 }
 
 pub fn bech32_to_canonical(addr: &str) -> CanonicalAddr {
@@ -26,111 +355,2733 @@ pub fn bech32_to_canonical(addr: &str) -> CanonicalAddr {
     CanonicalAddr(Binary(Vec::<u8>::from_base32(&data).unwrap()))
 }
 
-impl<T: Clone + Serialize> Permit<T> {
-    pub fn create_signed_tx(&self, msg_type: Option<String>) -> SignedTx<T> {
-        SignedTx::from_permit(self, msg_type)
+/// Signature checks validation runs, every cosmwasm Api implements it. Lets tests and
+/// off-chain tools validate with real crypto, the mock api accepts any signature and the
+/// Api trait can't be implemented outside of cosmwasm
+pub trait Verifier {
+    fn verify_secp256k1(
+        &self,
+        message_hash: &[u8],
+        signature: &[u8],
+        public_key: &[u8],
+    ) -> Result<bool, String>;
+
+    fn recover_secp256k1(
+        &self,
+        message_hash: &[u8],
+        signature: &[u8],
+        recovery_param: u8,
+    ) -> Result<Vec<u8>, String>;
+
+    fn verify_ed25519(
+        &self,
+        message: &[u8],
+        signature: &[u8],
+        public_key: &[u8],
+    ) -> Result<bool, String>;
+}
+
+impl<A: Api> Verifier for A {
+    fn verify_secp256k1(
+        &self,
+        message_hash: &[u8],
+        signature: &[u8],
+        public_key: &[u8],
+    ) -> Result<bool, String> {
+        self.secp256k1_verify(message_hash, signature, public_key)
+            .map_err(|err| err.to_string())
+    }
+
+    fn recover_secp256k1(
+        &self,
+        message_hash: &[u8],
+        signature: &[u8],
+        recovery_param: u8,
+    ) -> Result<Vec<u8>, String> {
+        self.secp256k1_recover_pubkey(message_hash, signature, recovery_param)
+            .map_err(|err| err.to_string())
+    }
+
+    fn verify_ed25519(
+        &self,
+        message: &[u8],
+        signature: &[u8],
+        public_key: &[u8],
+    ) -> Result<bool, String> {
+        self.ed25519_verify(message, signature, public_key)
+            .map_err(|err| err.to_string())
+    }
+}
+
+/// Verifies many (signature, pubkey) pairs over one precomputed sign-doc hash,
+/// malformed signatures or keys count as not verified
+pub fn verify_many<A: Api>(
+    api: &A,
+    sign_doc_hash: &[u8],
+    sigs_and_pubkeys: &[(Binary, Binary)],
+) -> Vec<bool> {
+    sigs_and_pubkeys
+        .iter()
+        .map(|(signature, pubkey)| {
+            api.secp256k1_verify(sign_doc_hash, signature.as_slice(), pubkey.as_slice())
+                .unwrap_or(false)
+        })
+        .collect()
+}
+
+impl<T: Clone + Serialize> Permit<T> {
+    pub fn create_signed_tx(&self, msg_type: Option<String>) -> SignedTx<T> {
+        SignedTx::from_permit(self, msg_type)
+    }
+}
+
+impl<T: Serialize> Permit<T> {
+    /// Signature schemes validation accepts
+    pub fn supported_schemes() -> Vec<SigScheme> {
+        SigScheme::ALL.to_vec()
+    }
+
+    /// Chain id used in the sign-doc
+    pub fn effective_chain_id(&self) -> &str {
+        self.chain_id.as_deref().unwrap_or("secret-4")
+    }
+
+    /// Sequence used in the sign-doc
+    pub fn effective_sequence(&self) -> Uint128 {
+        self.sequence.unwrap_or(Uint128::zero())
+    }
+
+    /// Account number used in the sign-doc
+    pub fn effective_account_number(&self) -> Uint128 {
+        self.account_number.unwrap_or(Uint128::zero())
+    }
+
+    /// Exact sign-doc bytes that get hashed and verified, useful to debug signature mismatches
+    pub fn signed_bytes(&self, msg_type: Option<String>) -> StdResult<Vec<u8>> {
+        SigningPayload::from_permit(self, msg_type).to_bytes()
+    }
+
+    /// Sign-doc wrapped with the EIP-191 prefix, what Metamask's personal_sign signs. Its
+    /// Keccak256 hash is what validation recovers Ethereum signers left out of the permit from,
+    /// ethermint keys sent along sign the sign-doc itself
+    pub fn eth_signed_bytes(&self, msg_type: Option<String>) -> StdResult<Vec<u8>> {
+        Ok(compact::eth_signed_message(&self.signed_bytes(msg_type)?))
+    }
+
+    /// Hash inputs of every scheme
+    pub fn preimages(&self, msg_type: Option<String>) -> StdResult<Preimages> {
+        let sign_doc = self.signed_bytes(msg_type)?;
+        let eth_keccak_input = if self.signature.pub_key.value.is_empty() {
+            compact::eth_signed_message(&sign_doc)
+        } else {
+            sign_doc.clone()
+        };
+        Ok(Preimages {
+            eth_keccak_input,
+            cosmos_sha256_input: sign_doc,
+        })
+    }
+
+    /// Lists every signed field of the sign-doc as (path, value) pairs, i.e. ("msgs.0.value.address", "secret1...")
+    pub fn signed_fields(&self, msg_type: Option<String>) -> StdResult<Vec<(String, String)>> {
+        flatten(&SignedTxRef::from_permit(self, msg_type))
+    }
+
+    /// Returns the permit signer
+    pub fn validate<A: Verifier>(
+        &self,
+        api: &A,
+        msg_type: Option<String>,
+    ) -> Result<PubKeyValue, PermitError> {
+        self.validate_with_prefix(api, msg_type, "secret")
+    }
+
+    /// Same as validate but hashes the sign-doc while it's serialized, it's never held whole
+    pub fn validate_streaming<A: Verifier>(
+        &self,
+        api: &A,
+        msg_type: Option<String>,
+    ) -> Result<PubKeyValue, PermitError> {
+        let hash = self
+            .signature
+            .pub_key
+            .hash_serialized_sign_doc(&SigningPayload::from_permit(self, msg_type))?;
+        Permit::<T>::verify_hash(api, &self.signature, &hash)
+    }
+
+    /// Returns the permit signer, as_humanaddr(None) then uses the given bech32 prefix
+    pub fn validate_with_prefix<A: Verifier>(
+        &self,
+        api: &A,
+        msg_type: Option<String>,
+        prefix: &str,
+    ) -> Result<PubKeyValue, PermitError> {
+        let signer = Permit::<T>::validate_sign_doc(
+            api,
+            &self.signature,
+            &SigningPayload::from_permit(self, msg_type),
+        )?;
+        Ok(signer.with_prefix(prefix))
+    }
+
+    /// Returns the permit signer's address
+    pub fn validate_canonical<A: Api>(
+        &self,
+        api: &A,
+        msg_type: Option<String>,
+    ) -> StdResult<CanonicalAddr> {
+        let signer = self.validate(api, msg_type)?;
+        signer.as_signer_canonical()
+    }
+
+    /// Returns the permit signer using the address prefix of the signed chain id
+    pub fn validate_as_humanaddr<A: Api>(
+        &self,
+        api: &A,
+        msg_type: Option<String>,
+    ) -> StdResult<HumanAddr> {
+        let canonical = self.validate_canonical(api, msg_type)?;
+        self.chain_humanaddr(&canonical)
+    }
+
+    /// Validates the permit and gathers everything a contract usually derives from it
+    pub fn validate_full<A: Api, F: Fn(&T) -> &str>(
+        &self,
+        api: &A,
+        msg_type: Option<String>,
+        extract_key: F,
+    ) -> StdResult<AuthContext> {
+        let signer = self.validate(api, msg_type)?;
+        let canonical = signer.as_signer_canonical()?;
+
+        Ok(AuthContext {
+            address: self.chain_humanaddr(&canonical)?,
+            canonical,
+            chain_id: self.effective_chain_id().to_string(),
+            permit_key: extract_key(&self.params).to_string(),
+            scheme: self.signature.pub_key.r#type.clone(),
+            sequence: self.effective_sequence(),
+        })
+    }
+
+    /// Encodes an address with the prefix of the signed chain id
+    fn chain_humanaddr(&self, canonical: &CanonicalAddr) -> StdResult<HumanAddr> {
+        let chain_id = self.effective_chain_id();
+        let prefix = prefix_for_chain_id(chain_id).ok_or_else(|| {
+            StdError::generic_err(format!("Unknown address prefix for chain id {}", chain_id))
+        })?;
+
+        Ok(HumanAddr(
+            bech32::encode(prefix, canonical.as_slice().to_base32(), Variant::Bech32)
+                .map_err(|err| StdError::generic_err(err.to_string()))?,
+        ))
+    }
+
+    /// Same as validate but for wallets that leave an empty memo out of the sign-doc
+    pub fn validate_omitting_empty_memo<A: Api>(
+        &self,
+        api: &A,
+        msg_type: Option<String>,
+    ) -> Result<PubKeyValue, PermitError> {
+        Permit::<T>::validate_sign_doc(
+            api,
+            &self.signature,
+            &SigningPayload::from_permit(self, msg_type).omit_empty_memo(),
+        )
+    }
+
+    /// Same as validate but rejects the permit once current is past its expiration
+    pub fn validate_with_time<A: Api>(
+        &self,
+        api: &A,
+        msg_type: Option<String>,
+        current: u64,
+    ) -> Result<PubKeyValue, PermitError> {
+        if let Some(expires) = self.expires {
+            if current > expires {
+                return Err(PermitError::Expired);
+            }
+        }
+
+        self.validate(api, msg_type)
+    }
+
+    /// Validates the permit only if it names the chain the contract runs on. The secret-4
+    /// default only fills the sign-doc, a permit without a chain id never matches here
+    pub fn validate_on_chain<A: Api>(
+        &self,
+        api: &A,
+        msg_type: Option<String>,
+        expected_chain_id: &str,
+    ) -> Result<PubKeyValue, PermitError> {
+        let signer = self.validate(api, msg_type)?;
+
+        if self.chain_id.as_deref() != Some(expected_chain_id) {
+            return Err(PermitError::ChainIdMismatch(expected_chain_id.to_string()));
+        }
+
+        Ok(signer)
+    }
+
+    /// Validates the permit only if its sequence is past last_seen, returns the sequence
+    /// so the contract can store it as the new last seen one
+    pub fn validate_with_sequence<A: Api>(
+        &self,
+        api: &A,
+        msg_type: Option<String>,
+        last_seen: Uint128,
+    ) -> Result<(PubKeyValue, Uint128), PermitError> {
+        let signer = self.validate(api, msg_type)?;
+
+        let sequence = self.effective_sequence();
+        if sequence <= last_seen {
+            return Err(PermitError::StaleSequence(last_seen));
+        }
+
+        Ok((signer, sequence))
+    }
+
+    /// Validates the permit only if it was signed for the requested action
+    pub fn validate_for_action<A: Api>(
+        &self,
+        api: &A,
+        msg_type: Option<String>,
+        expected: &str,
+    ) -> Result<PubKeyValue, PermitError> {
+        if self.action.as_deref() != Some(expected) {
+            return Err(PermitError::ActionMismatch(expected.to_string()));
+        }
+
+        self.validate(api, msg_type)
+    }
+
+    /// Validates the permit only if it was signed for the given contract code hash
+    pub fn validate_for_code_hash<A: Api>(
+        &self,
+        api: &A,
+        msg_type: Option<String>,
+        expected: &str,
+    ) -> Result<PubKeyValue, PermitError> {
+        if self.code_hash.as_deref() != Some(expected) {
+            return Err(PermitError::CodeHashMismatch);
+        }
+
+        self.validate(api, msg_type)
+    }
+
+    /// Same as validate but the permit must name its chain instead of defaulting to secret-4
+    pub fn validate_strict<A: Api>(
+        &self,
+        api: &A,
+        msg_type: Option<String>,
+    ) -> Result<PubKeyValue, PermitError> {
+        if self.chain_id.is_none() {
+            return Err(PermitError::MissingChainId);
+        }
+
+        self.validate(api, msg_type)
+    }
+
+    /// Validates the permit only if it was signed for one of the allowed chains
+    pub fn validate_with_chain_ids<A: Api>(
+        &self,
+        api: &A,
+        msg_type: Option<String>,
+        allowed: &[&str],
+    ) -> Result<PubKeyValue, PermitError> {
+        let chain_id = self.effective_chain_id();
+        if !allowed.contains(&chain_id) {
+            return Err(PermitError::ChainIdNotAllowed(chain_id.to_string()));
+        }
+
+        self.validate(api, msg_type)
+    }
+
+    /// Validates the permit only if its account number is within min..=max, checked before the signature
+    pub fn validate_with_account_range<A: Api>(
+        &self,
+        api: &A,
+        msg_type: Option<String>,
+        min: Uint128,
+        max: Uint128,
+    ) -> Result<PubKeyValue, PermitError> {
+        let account_number = self.effective_account_number();
+        if account_number < min || account_number > max {
+            return Err(PermitError::AccountNumberOutOfRange {
+                account_number,
+                min,
+                max,
+            });
+        }
+
+        self.validate(api, msg_type)
+    }
+
+    /// Validates the permit and reads the params as P, fields missing from P are ignored
+    pub fn validate_into<A: Api, P: DeserializeOwned>(
+        &self,
+        api: &A,
+        msg_type: Option<String>,
+    ) -> StdResult<(PubKeyValue, P)> {
+        let signer = self.validate(api, msg_type)?;
+        let params = from_slice(&compact::to_vec(&self.params)?)?;
+        Ok((signer, params))
+    }
+
+    /// Validates the permit then reads the base64 JSON embedded in one of the params as D
+    pub fn validate_and_decode<A: Api, D: DeserializeOwned, F: Fn(&T) -> &str>(
+        &self,
+        api: &A,
+        msg_type: Option<String>,
+        extract: F,
+    ) -> StdResult<(PubKeyValue, D)> {
+        let signer = self.validate(api, msg_type)?;
+        let data = Binary::from_base64(extract(&self.params))?;
+        Ok((signer, from_slice(data.as_slice())?))
+    }
+
+    /// Validates the permit and confirms that the address claimed in the params is the signer,
+    /// addresses are compared without their prefix
+    pub fn validate_signer_field<A: Api, F: Fn(&T) -> &str>(
+        &self,
+        api: &A,
+        msg_type: Option<String>,
+        extract: F,
+    ) -> StdResult<PubKeyValue> {
+        let signer = self.validate(api, msg_type)?;
+        let claimed = try_bech32_to_canonical(extract(&self.params))?;
+
+        if signer.as_signer_canonical()? != claimed {
+            return Err(StdError::generic_err(
+                "Signer does not match the address in the permit params",
+            ));
+        }
+
+        Ok(signer)
+    }
+
+    /// Checks the permit was signed by the claimed address, compared without its bech32 prefix
+    pub fn verify_address<A: Api>(
+        &self,
+        api: &A,
+        msg_type: Option<String>,
+        claimed: &HumanAddr,
+    ) -> StdResult<()> {
+        let signer = self.validate(api, msg_type)?;
+        let claimed = try_bech32_to_canonical(claimed.as_str())?;
+
+        if signer.as_signer_canonical()? != claimed {
+            return Err(StdError::generic_err(
+                "Permit was not signed by the claimed address",
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Same as validate but skips the signature verification if this signer
+    /// was already verified over the same sign-doc. Permits leaving their pubkey out are
+    /// never cached, their signer is only known once recovered
+    pub fn validate_cached<A: Verifier>(
+        &self,
+        api: &A,
+        cache: &mut ValidationCache,
+        msg_type: Option<String>,
+    ) -> StdResult<PubKeyValue> {
+        let signed_bytes = compact::to_vec(&SignedTxRef::from_permit(self, msg_type))?;
+        let key = (
+            self.signature.pub_key.value.0.clone(),
+            self.signature.pub_key.hash_sign_doc(&signed_bytes),
+        );
+
+        if key.0.is_empty() {
+            return Ok(Permit::<T>::verify_hash(api, &self.signature, &key.1)?);
+        }
+
+        if cache.verified.contains(&key) {
+            cache.hits += 1;
+            return Ok(PubKeyValue::new(self.signature.pub_key.value.clone())
+                .with_scheme(self.signature.pub_key.scheme()?));
+        }
+
+        let signer = Permit::<T>::verify_hash(api, &self.signature, &key.1)?;
+        cache.verified.insert(key);
+        Ok(signer)
+    }
+
+    /// Validates every permit in order and returns their signers, stopping at the first
+    /// invalid permit and reporting its index
+    pub fn validate_batch<A: Verifier>(
+        api: &A,
+        permits: &[Permit<T>],
+        msg_type: Option<String>,
+    ) -> StdResult<Vec<PubKeyValue>> {
+        permits
+            .iter()
+            .enumerate()
+            .map(|(index, permit)| {
+                permit.validate(api, msg_type.clone()).map_err(|err| {
+                    StdError::generic_err(format!("Permit at index {} is invalid: {}", index, err))
+                })
+            })
+            .collect()
+    }
+
+    pub fn validate_signed_tx<A: Verifier>(
+        api: &A,
+        signature: &PermitSignature,
+        signed_tx: &SignedTx<T>,
+    ) -> Result<PubKeyValue, PermitError> {
+        Permit::<T>::validate_sign_doc(api, signature, signed_tx)
+    }
+
+    fn validate_sign_doc<A: Verifier, S: Serialize>(
+        api: &A,
+        signature: &PermitSignature,
+        sign_doc: &S,
+    ) -> Result<PubKeyValue, PermitError> {
+        // Validate signature
+        let signed_bytes = compact::to_vec(sign_doc)?;
+        let signed_bytes_hash = signature.pub_key.hash_sign_doc(&signed_bytes);
+        Permit::<T>::verify_hash(api, signature, &signed_bytes_hash)
+    }
+
+    fn verify_hash<A: Verifier>(
+        api: &A,
+        signature: &PermitSignature,
+        signed_bytes_hash: &[u8],
+    ) -> Result<PubKeyValue, PermitError> {
+        let pubkey = &signature.pub_key.value;
+
+        let scheme = signature.pub_key.scheme()?;
+        let verified = match scheme {
+            SigScheme::EthSecp256k1 if pubkey.is_empty() => {
+                return Permit::<T>::recover_eth_signer(api, signature, signed_bytes_hash);
+            }
+            SigScheme::Secp256k1 | SigScheme::EthSecp256k1 => {
+                secp256k1::PublicKey::from_slice(pubkey.as_slice())
+                    .map_err(|err| PermitError::InvalidPubKey(err.to_string()))?;
+                api.verify_secp256k1(signed_bytes_hash, &signature.signature.0, &pubkey.0)
+            }
+            SigScheme::Ed25519 => {
+                if pubkey.len() != 32 {
+                    return Err(PermitError::InvalidPubKey(
+                        "Ed25519 keys must be 32 bytes".to_string(),
+                    ));
+                }
+                api.verify_ed25519(signed_bytes_hash, &signature.signature.0, &pubkey.0)
+            }
+        }
+        .map_err(|err| PermitError::VerifyFailed(err.to_string()))?;
+
+        if !verified {
+            return Err(PermitError::SignatureMismatch);
+        }
+
+        Ok(PubKeyValue::new(pubkey.clone()).with_scheme(scheme))
+    }
+
+    /// Recovers the key behind a 65 byte Ethereum signature, the caller still has to
+    /// check the derived address since any valid signature recovers some key
+    fn recover_eth_signer<A: Verifier>(
+        api: &A,
+        signature: &PermitSignature,
+        signed_bytes_hash: &[u8],
+    ) -> Result<PubKeyValue, PermitError> {
+        let sig = signature.signature.as_slice();
+        if sig.len() != 65 {
+            return Err(PermitError::InvalidPubKey(
+                "Only 65 byte recoverable signatures can leave the pubkey out".to_string(),
+            ));
+        }
+
+        // Wallets add 27 to the recovery id
+        let recovery_param = match sig[64] {
+            0 | 27 => 0,
+            1 | 28 => 1,
+            id => {
+                return Err(PermitError::VerifyFailed(format!(
+                    "Invalid recovery id {}",
+                    id
+                )))
+            }
+        };
+
+        let recovered = api
+            .recover_secp256k1(signed_bytes_hash, &sig[..64], recovery_param)
+            .map_err(|err| PermitError::VerifyFailed(err.to_string()))?;
+        if recovered.len() != 33 && recovered.len() != 65 {
+            return Err(PermitError::InvalidRecoveredKey(recovered.len()));
+        }
+        secp256k1::PublicKey::from_slice(&recovered)
+            .map_err(|err| PermitError::InvalidPubKey(err.to_string()))?;
+
+        Ok(PubKeyValue::new(Binary(recovered)).with_scheme(SigScheme::EthSecp256k1))
+    }
+}
+
+#[cfg(feature = "bincode")]
+impl<T: Serialize + DeserializeOwned> Permit<T> {
+    /// Bincode encoded permit in base64, smaller than JSON when sharing permits through QR codes
+    pub fn to_compact_base64(&self) -> StdResult<String> {
+        let bytes =
+            bincode::serialize(self).map_err(|err| StdError::serialize_err("Permit", err))?;
+        Ok(Binary(bytes).to_base64())
+    }
+
+    pub fn from_compact_base64(encoded: &str) -> StdResult<Self> {
+        let bytes = Binary::from_base64(encoded)?;
+        bincode::deserialize(bytes.as_slice()).map_err(|err| StdError::parse_err("Permit", err))
+    }
+}
+
+impl<T: Serialize + ParamsVersion> Permit<T> {
+    /// Rejects permits whose params version isn't the expected one before validating them
+    pub fn validate_versioned<A: Api>(
+        &self,
+        api: &A,
+        msg_type: Option<String>,
+    ) -> Result<PubKeyValue, PermitError> {
+        let version = self.params.version();
+        if version != T::VERSION {
+            return Err(PermitError::UnsupportedVersion {
+                version,
+                expected: T::VERSION,
+            });
+        }
+
+        self.validate(api, msg_type)
+    }
+}
+
+impl<T: Serialize> Permit<Vec<T>> {
+    /// Permit whose sign-doc has one message per params entry, validate it with validate_multi
+    pub fn from_multi(params: Vec<T>, signature: PermitSignature) -> Self {
+        Self {
+            params,
+            signature,
+            account_number: None,
+            chain_id: None,
+            sequence: None,
+            memo: None,
+            expires: None,
+            code_hash: None,
+            action: None,
+            fee: None,
+        }
+    }
+
+    /// Exact bytes of the sign-doc with one message per params entry
+    pub fn signed_bytes_multi(&self, msg_type: Option<String>) -> StdResult<Vec<u8>> {
+        compact::to_vec(&SignedTxRef::from_permit_multi(self, msg_type))
+    }
+
+    /// Returns the single signer of all the messages, validate would instead
+    /// expect the params as one message holding an array
+    pub fn validate_multi<A: Api>(
+        &self,
+        api: &A,
+        msg_type: Option<String>,
+    ) -> Result<PubKeyValue, PermitError> {
+        Permit::<Vec<T>>::validate_sign_doc(
+            api,
+            &self.signature,
+            &SignedTxRef::from_permit_multi(self, msg_type),
+        )
+    }
+
+    /// Validates the whole multi message sign-doc, then checks that the message at index
+    /// is the expected one, for contracts that only care about their own message
+    pub fn validate_msg_at<A: Api>(
+        &self,
+        api: &A,
+        msg_type: Option<String>,
+        index: usize,
+        expected: &T,
+    ) -> Result<PubKeyValue, PermitError>
+    where
+        T: PartialEq,
+    {
+        let signer = self.validate_multi(api, msg_type)?;
+        if self.params.get(index) != Some(expected) {
+            return Err(PermitError::MessageMismatch(index));
+        }
+
+        Ok(signer)
+    }
+}
+
+impl Permit<Binary> {
+    /// Validates a permit whose params are the sha256 hash of the full params,
+    /// the full params are sent next to the permit and must match the signed commitment
+    pub fn validate_committed<A: Api, P: Serialize>(
+        &self,
+        api: &A,
+        msg_type: Option<String>,
+        full_params: &P,
+    ) -> StdResult<PubKeyValue> {
+        let commitment = sha_256(&compact::to_vec(full_params)?);
+        if self.params.as_slice() != commitment {
+            return Err(StdError::generic_err(
+                "Params do not match the signed commitment",
+            ));
+        }
+
+        Ok(self.validate(api, msg_type)?)
+    }
+}
+
+#[cfg(test)]
+mod signature_tests {
+    use super::*;
+    use crate::compact::RawParams;
+    use crate::transaction::{Coin, PubKey, ED25519_TYPE, ETH_SECP256K1_TYPE};
+    use cosmwasm_std::testing::mock_dependencies;
+    use cosmwasm_std::{to_binary, to_vec, Uint128};
+
+    /// SN mock deps accept any signature, this checks them the way the chain does
+    struct RealCrypto;
+
+    impl Verifier for RealCrypto {
+        fn verify_secp256k1(
+            &self,
+            message_hash: &[u8],
+            signature: &[u8],
+            public_key: &[u8],
+        ) -> Result<bool, String> {
+            let message =
+                secp256k1::Message::from_slice(message_hash).map_err(|err| err.to_string())?;
+            let signature =
+                secp256k1::Signature::from_compact(signature).map_err(|err| err.to_string())?;
+            let public_key =
+                secp256k1::PublicKey::from_slice(public_key).map_err(|err| err.to_string())?;
+            Ok(secp256k1::Secp256k1::verification_only()
+                .verify(&message, &signature, &public_key)
+                .is_ok())
+        }
+
+        fn recover_secp256k1(
+            &self,
+            message_hash: &[u8],
+            signature: &[u8],
+            recovery_param: u8,
+        ) -> Result<Vec<u8>, String> {
+            use secp256k1::recovery::{RecoverableSignature, RecoveryId};
+
+            let id =
+                RecoveryId::from_i32(i32::from(recovery_param)).map_err(|err| err.to_string())?;
+            let signature =
+                RecoverableSignature::from_compact(signature, id).map_err(|err| err.to_string())?;
+            let message =
+                secp256k1::Message::from_slice(message_hash).map_err(|err| err.to_string())?;
+            let pubkey = secp256k1::Secp256k1::verification_only()
+                .recover(&message, &signature)
+                .map_err(|err| err.to_string())?;
+            Ok(pubkey.serialize().to_vec())
+        }
+
+        fn verify_ed25519(
+            &self,
+            message: &[u8],
+            signature: &[u8],
+            public_key: &[u8],
+        ) -> Result<bool, String> {
+            use ed25519_dalek::Verifier;
+
+            let signature =
+                ed25519_dalek::Signature::from_bytes(signature).map_err(|err| err.to_string())?;
+            let public_key =
+                ed25519_dalek::PublicKey::from_bytes(public_key).map_err(|err| err.to_string())?;
+            Ok(public_key.verify(message, &signature).is_ok())
+        }
+    }
+
+    fn secp256k1_verifies(hash: &[u8], signature: &Binary, pubkey: &Binary) -> bool {
+        RealCrypto
+            .verify_secp256k1(hash, signature.as_slice(), pubkey.as_slice())
+            .unwrap_or(false)
+    }
+
+    fn ed25519_verifies(message: &[u8], signature: &Binary, pubkey: &Binary) -> bool {
+        RealCrypto
+            .verify_ed25519(message, signature.as_slice(), pubkey.as_slice())
+            .unwrap_or(false)
+    }
+
+    /// Key recovered from a 65 byte signature the way the chain does it
+    fn secp256k1_recovers(hash: &[u8], signature: &Binary) -> Option<Binary> {
+        let signature = signature.as_slice();
+        let recovery_param = *signature.get(64)? % 27;
+        RealCrypto
+            .recover_secp256k1(hash, &signature[..64], recovery_param)
+            .ok()
+            .map(Binary)
+    }
+
+    /// Whether the permit signature holds over its sign-doc on chain
+    fn verifies_on_chain<T: Serialize>(permit: &Permit<T>, msg_type: Option<String>) -> bool {
+        sign_doc_verifies(
+            &permit.signature,
+            &SignedTxRef::from_permit(permit, msg_type),
+        )
+    }
+
+    fn sign_doc_verifies<S: Serialize>(signature: &PermitSignature, sign_doc: &S) -> bool {
+        let signed_bytes = compact::to_vec(sign_doc).unwrap();
+        secp256k1_verifies(
+            &signature.pub_key.hash_sign_doc(&signed_bytes),
+            &signature.signature,
+            &signature.pub_key.value,
+        )
+    }
+
+    #[remain::sorted]
+    #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+    #[serde(rename_all = "snake_case")]
+    struct TestPermitMsg {
+        pub address: String,
+        pub some_number: Uint128,
+    }
+
+    type TestPermit = Permit<TestPermitMsg>;
+
+    const ADDRESS: &str = "secret102nasmxnxvwp5agc4lp3flc6s23335xm8g7gn9";
+    const PUBKEY: &str = "A0qzJ3s16OKUfn1KFyh533vBnBOQIT0jm+R/FBobJCfa";
+    const SIGNED_TX: &str =
+        "4pZtghyHKHHmwiGNC5JD8JxCJiO+44j6GqaLPc19Q7lt85tr0IRZHYcnc0pkokIds8otxU9rcuvPXb0+etLyVA==";
+
+    // Use secretcli tx sign-doc file --from account
+    //{
+    //  "account_number": "0",
+    //  "chain_id": "pulsar-1",
+    //  "fee": {
+    //      "amount": [{
+    //          "amount": "0",
+    //          "denom": "uscrt"
+    //      }],
+    //      "gas": "1"
+    //  },
+    //  "memo": "",
+    //  "msgs": [{
+    //      "type": "signature_proof",
+    //      "value": {
+    //          "address": "secret102nasmxnxvwp5agc4lp3flc6s23335xm8g7gn9",
+    //          "some_number": "10"
+    //      }
+    //  }],
+    //  "sequence": "0"
+    // }
+
+    #[test]
+    fn test_signed_tx() {
+        let mut permit = TestPermit {
+            params: TestPermitMsg {
+                address: ADDRESS.to_string(),
+                some_number: Uint128(10),
+            },
+            chain_id: Some("pulsar-1".to_string()),
+            sequence: None,
+            signature: PermitSignature {
+                pub_key: PubKey::new(Binary::from_base64(PUBKEY).unwrap()),
+                signature: Binary::from_base64(SIGNED_TX).unwrap(),
+            },
+            account_number: None,
+            memo: None,
+            expires: None,
+            code_hash: None,
+            action: None,
+            fee: None,
+        };
+
+        let deps = mock_dependencies(20, &[]);
+        let addr = permit.validate(&deps.api, None).unwrap();
+        assert_eq!(
+            addr.as_humanaddr(None).unwrap(),
+            HumanAddr(ADDRESS.to_string())
+        );
+        assert_eq!(addr.as_canonical().unwrap(), bech32_to_canonical(ADDRESS));
+
+        permit.params.some_number = Uint128(100);
+        // NOTE: SN mock deps dont have a valid working implementation of the dep functons for some reason
+        //assert!(permit.validate(&deps.api, None).is_err());
+    }
+
+    #[test]
+    fn signed_tx_preimages() {
+        let mut permit = PermitBuilder::new()
+            .params(TestPermitMsg {
+                address: ADDRESS.to_string(),
+                some_number: Uint128(10),
+            })
+            .chain_id("pulsar-1")
+            .signature(PermitSignature::from_base64(PUBKEY, SIGNED_TX).unwrap())
+            .build()
+            .unwrap();
+
+        let sign_doc = concat!(
+            r#"{"account_number":"0","chain_id":"pulsar-1","#,
+            r#""fee":{"amount":[{"amount":"0","denom":"uscrt"}],"gas":"1"},"memo":"","#,
+            r#""msgs":[{"type":"signature_proof","value":{"#,
+            r#""address":"secret102nasmxnxvwp5agc4lp3flc6s23335xm8g7gn9","some_number":"10"}}],"#,
+            r#""sequence":"0"}"#
+        );
+        let preimages = permit.preimages(None).unwrap();
+        assert_eq!(preimages.cosmos_sha256_input, sign_doc.as_bytes());
+        // Ethermint keys sent along hash the sign-doc itself
+        assert_eq!(preimages.eth_keccak_input, sign_doc.as_bytes());
+
+        // Recovered signers hash the EIP-191 wrapped sign-doc
+        permit.signature.pub_key = PubKey {
+            r#type: ETH_SECP256K1_TYPE.to_string(),
+            value: Binary(vec![]),
+        };
+        let preimages = permit.preimages(None).unwrap();
+        assert_eq!(
+            preimages.eth_keccak_input,
+            format!(
+                "\x19Ethereum Signed Message:\n{}{}",
+                sign_doc.len(),
+                sign_doc
+            )
+            .as_bytes()
+        );
+        assert_eq!(
+            preimages.eth_keccak_input,
+            permit.eth_signed_bytes(None).unwrap()
+        );
+        assert_eq!(
+            permit.signature.pub_key.hash_sign_doc(sign_doc.as_bytes()),
+            <sha3::Keccak256 as sha3::Digest>::digest(&preimages.eth_keccak_input).as_slice()
+        );
+    }
+
+    #[test]
+    fn permit_builder() {
+        let permit = PermitBuilder::new()
+            .params(TestPermitMsg {
+                address: ADDRESS.to_string(),
+                some_number: Uint128(10),
+            })
+            .chain_id("pulsar-1")
+            .signature(PermitSignature::from_base64(PUBKEY, SIGNED_TX).unwrap())
+            .build()
+            .unwrap();
+
+        let built = PermitBuilder::new()
+            .params(TestPermitMsg {
+                address: ADDRESS.to_string(),
+                some_number: Uint128(10),
+            })
+            .chain_id("pulsar-1")
+            .signature(PermitSignature {
+                pub_key: PubKey::new(Binary::from_base64(PUBKEY).unwrap()),
+                signature: Binary::from_base64(SIGNED_TX).unwrap(),
+            })
+            .build()
+            .unwrap();
+        assert_eq!(built, permit);
+        assert_eq!(built.create_signed_tx(None), permit.create_signed_tx(None));
+
+        assert_eq!(
+            PermitBuilder::new()
+                .params(permit.params.clone())
+                .build()
+                .unwrap_err(),
+            StdError::generic_err("Permit signature is missing")
+        );
+        assert_eq!(
+            PermitBuilder::<TestPermitMsg>::new()
+                .signature(permit.signature)
+                .build()
+                .unwrap_err(),
+            StdError::generic_err("Permit params are missing")
+        );
+    }
+
+    #[test]
+    fn permit_json_shape() {
+        let permit = PermitBuilder::new()
+            .params(TestPermitMsg {
+                address: ADDRESS.to_string(),
+                some_number: Uint128(10),
+            })
+            .chain_id("pulsar-1")
+            .signature(PermitSignature::from_base64(PUBKEY, SIGNED_TX).unwrap())
+            .build()
+            .unwrap();
+
+        // The permit itself is not sorted, only the sign-doc is
+        let json = String::from_utf8(to_vec(&permit).unwrap()).unwrap();
+        assert_eq!(
+            json,
+            concat!(
+                r#"{"params":{"address":"secret102nasmxnxvwp5agc4lp3flc6s23335xm8g7gn9","some_number":"10"},"#,
+                r#""signature":{"pub_key":{"type":"tendermint/PubKeySecp256k1","value":"A0qzJ3s16OKUfn1KFyh533vBnBOQIT0jm+R/FBobJCfa"},"#,
+                r#""signature":"4pZtghyHKHHmwiGNC5JD8JxCJiO+44j6GqaLPc19Q7lt85tr0IRZHYcnc0pkokIds8otxU9rcuvPXb0+etLyVA=="},"#,
+                r#""account_number":null,"chain_id":"pulsar-1","sequence":null,"memo":null,"expires":null,"code_hash":null,"action":null,"fee":null}"#
+            )
+        );
+    }
+
+    #[cfg(feature = "bincode")]
+    #[test]
+    fn compact_encoding() {
+        let permit = PermitBuilder::new()
+            .params(TestPermitMsg {
+                address: ADDRESS.to_string(),
+                some_number: Uint128(10),
+            })
+            .chain_id("pulsar-1")
+            .signature(PermitSignature::from_base64(PUBKEY, SIGNED_TX).unwrap())
+            .build()
+            .unwrap();
+
+        let compact = permit.to_compact_base64().unwrap();
+        assert_eq!(TestPermit::from_compact_base64(&compact).unwrap(), permit);
+        assert!(compact.len() < to_binary(&permit).unwrap().to_base64().len());
+        assert!(TestPermit::from_compact_base64("AAAA").is_err());
+    }
+
+    #[test]
+    fn unsupported_scheme() {
+        let mut permit = PermitBuilder::new()
+            .params(TestPermitMsg {
+                address: ADDRESS.to_string(),
+                some_number: Uint128(10),
+            })
+            .chain_id("pulsar-1")
+            .signature(PermitSignature::from_base64(PUBKEY, SIGNED_TX).unwrap())
+            .build()
+            .unwrap();
+        permit.signature.pub_key.r#type = "tendermint/PubKeySr25519".to_string();
+
+        assert_eq!(
+            TestPermit::supported_schemes(),
+            vec![
+                SigScheme::Secp256k1,
+                SigScheme::EthSecp256k1,
+                SigScheme::Ed25519
+            ]
+        );
+
+        let deps = mock_dependencies(20, &[]);
+        let err = permit.validate(&deps.api, None).unwrap_err();
+        assert_eq!(
+            err,
+            PermitError::UnsupportedScheme("tendermint/PubKeySr25519".to_string())
+        );
+        assert_eq!(
+            StdError::from(err),
+            StdError::generic_err(
+                "Unsupported signature scheme tendermint/PubKeySr25519, supported schemes: tendermint/PubKeySecp256k1, ethermint/PubKeyEthSecp256k1, tendermint/PubKeyEd25519"
+            )
+        );
+    }
+
+    #[test]
+    fn permit_errors() {
+        let mut permit = PermitBuilder::new()
+            .params(TestPermitMsg {
+                address: ADDRESS.to_string(),
+                some_number: Uint128(10),
+            })
+            .chain_id("pulsar-1")
+            .signature(PermitSignature::from_base64(PUBKEY, SIGNED_TX).unwrap())
+            .build()
+            .unwrap();
+        let deps = mock_dependencies(20, &[]);
+
+        assert_eq!(
+            StdError::from(PermitError::SignatureMismatch),
+            StdError::generic_err("Signature verification failed")
+        );
+
+        assert_eq!(
+            permit
+                .validate(&RealCrypto, None)
+                .unwrap()
+                .as_canonical()
+                .unwrap(),
+            bech32_to_canonical(ADDRESS)
+        );
+
+        // Signed over other params
+        permit.params.some_number = Uint128(100);
+        assert_eq!(
+            permit.validate(&RealCrypto, None).unwrap_err(),
+            PermitError::SignatureMismatch
+        );
+        permit.params.some_number = Uint128(10);
+
+        permit.signature.signature = Binary(vec![1; 10]);
+        assert_eq!(
+            permit.validate(&RealCrypto, None).unwrap_err(),
+            PermitError::VerifyFailed("secp: malformed signature".to_string())
+        );
+        permit.signature.signature = Binary::from_base64(SIGNED_TX).unwrap();
+
+        permit.signature.pub_key.value = Binary(vec![4; 33]);
+        assert!(matches!(
+            permit.validate(&deps.api, None).unwrap_err(),
+            PermitError::InvalidPubKey(_)
+        ));
+    }
+
+    #[test]
+    fn cached_validation() {
+        let permit = PermitBuilder::new()
+            .params(TestPermitMsg {
+                address: ADDRESS.to_string(),
+                some_number: Uint128(10),
+            })
+            .chain_id("pulsar-1")
+            .signature(PermitSignature::from_base64(PUBKEY, SIGNED_TX).unwrap())
+            .build()
+            .unwrap();
+
+        let deps = mock_dependencies(20, &[]);
+        let mut cache = ValidationCache::new();
+
+        let first = permit.validate_cached(&deps.api, &mut cache, None).unwrap();
+        assert_eq!(cache.hits(), 0);
+        assert_eq!(cache.len(), 1);
+
+        let second = permit.validate_cached(&deps.api, &mut cache, None).unwrap();
+        assert_eq!(cache.hits(), 1);
+        assert_eq!(cache.len(), 1);
+        assert_eq!(
+            first.as_canonical().unwrap(),
+            second.as_canonical().unwrap()
+        );
+        assert_eq!(second.as_canonical().unwrap(), bech32_to_canonical(ADDRESS));
+    }
+
+    const INJ_ADDRESS: &str = "inj1huuzdwg44hklf35afy20rnt855msxftq3dlzlw";
+    const INJ_SIGNED_TX: &str =
+        "mQjCisM3n22e0ligqZo5jlXPjwfy6Jz+Qbfc93NJfetl6ZxnomrAUfWwogq9y0kEruldmNGCg5ECnwztl0jLcQ==";
+
+    #[test]
+    fn eth_secp256k1_pubkey() {
+        let mut permit = PermitBuilder::new()
+            .params(TestPermitMsg {
+                address: INJ_ADDRESS.to_string(),
+                some_number: Uint128(10),
+            })
+            .chain_id("injective-1")
+            .signature(PermitSignature {
+                pub_key: PubKey {
+                    r#type: ETH_SECP256K1_TYPE.to_string(),
+                    value: Binary::from_base64(SCOPED_PUBKEY).unwrap(),
+                },
+                signature: Binary::from_base64(INJ_SIGNED_TX).unwrap(),
+            })
+            .build()
+            .unwrap();
+
+        let deps = mock_dependencies(20, &[]);
+        assert_eq!(
+            permit.validate_as_humanaddr(&deps.api, None).unwrap(),
+            HumanAddr(INJ_ADDRESS.to_string())
+        );
+
+        let signer = permit.validate(&deps.api, None).unwrap();
+        assert_eq!(
+            signer.as_eth_address().unwrap(),
+            "0xbf3826b915adedf4c69d4914f1cd67a537032560"
+        );
+        assert_eq!(signer.as_binary(), &permit.signature.pub_key.value);
+        assert_eq!(signer.to_base64(), SCOPED_PUBKEY);
+        assert_eq!(
+            PubKeyValue::new(Binary::from_base64(&signer.to_base64()).unwrap()),
+            signer
+        );
+        assert!(permit
+            .validate_signer_field(&deps.api, None, |params| &params.address)
+            .is_ok());
+
+        // Signed over the Keccak256 hash, so the tendermint type cant verify it
+        assert!(verifies_on_chain(&permit, None));
+        permit.signature.pub_key = PubKey::new(Binary::from_base64(SCOPED_PUBKEY).unwrap());
+        assert!(!verifies_on_chain(&permit, None));
+    }
+
+    // Private key 1 signing the sign-doc with personal_sign, the Keccak256 hash of the
+    // EIP-191 wrapped sign-doc with the recovery byte appended
+    const RECOVERABLE_SIGNED_TX: &str =
+        "xUR5VLFcfZolYcTMl9StWcEA+YDgTmTpFY8vLo5vFQE4wHzNPQEI4mJ+i72gG0mjSsj48GtC8gZzeDIkZd1H2xw=";
+
+    #[test]
+    fn recoverable_eth_signature() {
+        let mut permit = PermitBuilder::new()
+            .params(TestPermitMsg {
+                address: "0x7e5f4552091a69125d5dfcb7b8c2659029395bdf".to_string(),
+                some_number: Uint128(10),
+            })
+            .chain_id("injective-1")
+            .signature(PermitSignature {
+                pub_key: PubKey {
+                    r#type: ETH_SECP256K1_TYPE.to_string(),
+                    value: Binary(vec![]),
+                },
+                signature: Binary::from_base64(RECOVERABLE_SIGNED_TX).unwrap(),
+            })
+            .build()
+            .unwrap();
+
+        // The chain recovers private key 1 from the signature
+        let hash = permit
+            .signature
+            .pub_key
+            .hash_sign_doc(&permit.signed_bytes(None).unwrap());
+        let eth_signed = permit.eth_signed_bytes(None).unwrap();
+        assert_eq!(
+            hash.as_slice(),
+            <sha3::Keccak256 as sha3::Digest>::digest(&eth_signed).as_slice()
+        );
+        let recovered = secp256k1_recovers(&hash, &permit.signature.signature).unwrap();
+        assert_eq!(recovered, Binary::from_base64(KEY_ONE_PUBKEY).unwrap());
+
+        // Sending the key along signs the sign-doc itself, the ethermint way
+        let with_key = PubKey {
+            r#type: ETH_SECP256K1_TYPE.to_string(),
+            value: recovered.clone(),
+        };
+        assert_ne!(
+            with_key.hash_sign_doc(&permit.signed_bytes(None).unwrap()),
+            hash
+        );
+        let signer = PubKeyValue::new(recovered).with_scheme(SigScheme::EthSecp256k1);
+        assert_eq!(
+            signer.as_eth_address().unwrap(),
+            "0x7e5f4552091a69125d5dfcb7b8c2659029395bdf"
+        );
+
+        // Recovered through validate with real crypto
+        assert_eq!(
+            permit
+                .validate(&RealCrypto, None)
+                .unwrap()
+                .as_eth_address()
+                .unwrap(),
+            "0x7e5f4552091a69125d5dfcb7b8c2659029395bdf"
+        );
+
+        // SN mock deps recover an empty pubkey, which is rejected
+        let deps = mock_dependencies(20, &[]);
+        assert_eq!(
+            permit.validate(&deps.api, None).unwrap_err(),
+            PermitError::InvalidRecoveredKey(0)
+        );
+        assert_eq!(
+            StdError::from(PermitError::InvalidRecoveredKey(0)),
+            StdError::generic_err("Recovered pubkey is 0 bytes long, expected 33 or 65")
+        );
+
+        // Recovered signers are never cached
+        let mut cache = ValidationCache::new();
+        assert!(permit.validate_cached(&deps.api, &mut cache, None).is_err());
+        assert!(cache.is_empty());
+
+        let mut signature = permit.signature.signature.0.clone();
+        signature[64] = 29;
+        permit.signature.signature = Binary(signature.clone());
+        assert!(matches!(
+            permit.validate(&deps.api, None),
+            Err(PermitError::VerifyFailed(_))
+        ));
+
+        signature.pop();
+        permit.signature.signature = Binary(signature);
+        assert!(matches!(
+            permit.validate(&deps.api, None),
+            Err(PermitError::InvalidPubKey(_))
+        ));
+    }
+
+    fn inj_typed_permit() -> Permit<TestPermitMsg> {
+        PermitBuilder::new()
+            .params(TestPermitMsg {
+                address: INJ_ADDRESS.to_string(),
+                some_number: Uint128(10),
+            })
+            .chain_id("injective-1")
+            .signature(PermitSignature {
+                pub_key: PubKey {
+                    r#type: ETH_SECP256K1_TYPE.to_string(),
+                    value: Binary::from_base64(SCOPED_PUBKEY).unwrap(),
+                },
+                signature: Binary::from_base64(INJ_SIGNED_TX).unwrap(),
+            })
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn raw_params() {
+        let typed = inj_typed_permit();
+        let raw = PermitBuilder::new()
+            .params(RawParams::new(&compact::to_vec(&typed.params).unwrap()).unwrap())
+            .chain_id("injective-1")
+            .signature(typed.signature.clone())
+            .build()
+            .unwrap();
+        assert_eq!(
+            raw.params.as_str(),
+            format!(r#"{{"address":"{}","some_number":"10"}}"#, INJ_ADDRESS)
+        );
+        assert_eq!(
+            raw.signed_bytes(None).unwrap(),
+            typed.signed_bytes(None).unwrap()
+        );
+
+        let deps = mock_dependencies(20, &[]);
+        assert_eq!(
+            raw.validate_as_humanaddr(&deps.api, None).unwrap(),
+            HumanAddr(INJ_ADDRESS.to_string())
+        );
+
+        assert!(RawParams::new(b"{\"address\":").is_err());
+        assert!(RawParams::new(b"{} {}").is_err());
+    }
+
+    #[test]
+    fn raw_params_from_permit_json() {
+        let typed = inj_typed_permit();
+        let json = String::from_utf8(to_vec(&typed).unwrap()).unwrap();
+
+        // Relayers parse permits they don't know the params of
+        let raw: Permit<RawParams> = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            raw.params.as_str(),
+            format!(r#"{{"address":"{}","some_number":"10"}}"#, INJ_ADDRESS)
+        );
+        assert_eq!(
+            raw.signed_bytes(None).unwrap(),
+            typed.signed_bytes(None).unwrap()
+        );
+        let deps = mock_dependencies(20, &[]);
+        assert_eq!(
+            raw.validate_as_humanaddr(&deps.api, None).unwrap(),
+            HumanAddr(INJ_ADDRESS.to_string())
+        );
+
+        // Whitespace around the params isn't signed
+        let spaced = json.replace(r#""some_number":"10""#, r#" "some_number" : "10" "#);
+        assert_ne!(spaced, json);
+        let raw: Permit<RawParams> = serde_json::from_str(&spaced).unwrap();
+        assert_eq!(
+            raw.signed_bytes(None).unwrap(),
+            typed.signed_bytes(None).unwrap()
+        );
+
+        let params = format!(r#"{{"address":"{}","some_number":"10"}}"#, INJ_ADDRESS);
+        for (invalid, err) in [
+            (
+                format!(r#"{{"some_number":"10","address":"{}"}}"#, INJ_ADDRESS),
+                "JSON must be compact with sorted keys",
+            ),
+            (
+                format!(
+                    r#"{{"address":"{}","address":"{}"}}"#,
+                    INJ_ADDRESS, INJ_ADDRESS
+                ),
+                "Duplicate key \"address\"",
+            ),
+            (
+                format!(r#"{{"address":"{}","some_number":1.5}}"#, INJ_ADDRESS),
+                "f64 is not supported in a sign-doc",
+            ),
+        ] {
+            let json = json.replace(&params, &invalid);
+            let parsed = serde_json::from_str::<Permit<RawParams>>(&json);
+            assert!(parsed.unwrap_err().to_string().contains(err), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn sign_doc_hashes() {
+        let mut permit = PermitBuilder::new()
+            .params(TestPermitMsg {
+                address: INJ_ADDRESS.to_string(),
+                some_number: Uint128(10),
+            })
+            .chain_id("injective-1")
+            .signature(PermitSignature {
+                pub_key: PubKey {
+                    r#type: ETH_SECP256K1_TYPE.to_string(),
+                    value: Binary::from_base64(SCOPED_PUBKEY).unwrap(),
+                },
+                signature: Binary::from_base64(INJ_SIGNED_TX).unwrap(),
+            })
+            .build()
+            .unwrap();
+
+        let signed_tx = SignedTx::from_permit(&permit, None);
+        let signed_bytes = permit.signed_bytes(None).unwrap();
+        assert_eq!(
+            signed_tx.eth_hash().unwrap(),
+            permit.signature.pub_key.hash_sign_doc(&signed_bytes)
+        );
+        assert_eq!(signed_tx.sha256_hash().unwrap(), sha_256(&signed_bytes));
+
+        permit.signature.pub_key = PubKey::new(Binary::from_base64(SCOPED_PUBKEY).unwrap());
+        assert_eq!(
+            signed_tx.sha256_hash().unwrap(),
+            permit.signature.pub_key.hash_sign_doc(&signed_bytes)
+        );
+        assert_ne!(
+            signed_tx.sha256_hash().unwrap(),
+            signed_tx.eth_hash().unwrap()
+        );
+    }
+
+    #[test]
+    fn stack_array() {
+        let msg = TestPermitMsg {
+            address: ADDRESS.to_string(),
+            some_number: Uint128(10),
+        };
+        let (array, len) = compact::to_array::<256, _>(&msg).unwrap();
+        assert_eq!(&array[..len], compact::to_vec(&msg).unwrap().as_slice());
+        assert_eq!(len, 78);
+        assert!(array[len..].iter().all(|b| *b == 0));
+
+        // Exactly fits
+        let (exact, exact_len) = compact::to_array::<78, _>(&msg).unwrap();
+        assert_eq!((&exact[..], exact_len), (&array[..len], len));
+
+        assert_eq!(
+            compact::to_array::<77, _>(&msg).unwrap_err(),
+            StdError::serialize_err(
+                std::any::type_name::<TestPermitMsg>(),
+                "Buffer full, the output doesn't fit in 77 bytes"
+            )
+        );
+    }
+
+    #[test]
+    fn streaming_validation() {
+        let permit = scoped_permit();
+
+        let payload = SigningPayload::from_permit(&permit, None);
+        let signed_bytes = permit.signed_bytes(None).unwrap();
+        let hash = permit
+            .signature
+            .pub_key
+            .hash_serialized_sign_doc(&payload)
+            .unwrap();
+        assert_eq!(hash, permit.signature.pub_key.hash_sign_doc(&signed_bytes));
+        assert!(secp256k1_verifies(
+            &hash,
+            &permit.signature.signature,
+            &permit.signature.pub_key.value
+        ));
+
+        let deps = mock_dependencies(20, &[]);
+        assert_eq!(
+            permit.validate_streaming(&deps.api, None).unwrap(),
+            permit.validate(&deps.api, None).unwrap()
+        );
+
+        // Every scheme hashes the same bytes as the buffered path
+        for pub_key in [
+            PubKey {
+                r#type: ETH_SECP256K1_TYPE.to_string(),
+                value: permit.signature.pub_key.value.clone(),
+            },
+            PubKey {
+                r#type: ETH_SECP256K1_TYPE.to_string(),
+                value: Binary(vec![]),
+            },
+            PubKey {
+                r#type: ED25519_TYPE.to_string(),
+                value: Binary::from_base64(ED25519_PUBKEY).unwrap(),
+            },
+        ] {
+            assert_eq!(
+                pub_key.hash_serialized_sign_doc(&payload).unwrap(),
+                pub_key.hash_sign_doc(&signed_bytes),
+                "{}",
+                pub_key.r#type
+            );
+        }
+    }
+
+    #[remain::sorted]
+    #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+    #[serde(rename_all = "snake_case")]
+    struct VersionedMsg {
+        pub address: String,
+        pub version: u32,
+    }
+
+    impl ParamsVersion for VersionedMsg {
+        const VERSION: u32 = 2;
+
+        fn version(&self) -> u32 {
+            self.version
+        }
+    }
+
+    const V1_SIGNED_TX: &str =
+        "9sAvudXLwp4Qd3ZG6ORMT3JXDkz1vvpkstEnIoHADE5H1vUVMdr/ZNK065vZO1Qh5tMNoRLVClg9GcZ7dcX7zQ==";
+    const V2_SIGNED_TX: &str =
+        "bNFuQIxS6fcUTrE3lypDE9UikJrsBfxxPykTsv4sCKtzTnpI3zxlTzrRfuuHianVWqG4t5i/OW8QW6IRrQxWfA==";
+
+    #[test]
+    fn versioned_params() {
+        let mut permit = PermitBuilder::new()
+            .params(VersionedMsg {
+                address: SCOPED_ADDRESS.to_string(),
+                version: 2,
+            })
+            .signature(PermitSignature::from_base64(SCOPED_PUBKEY, V2_SIGNED_TX).unwrap())
+            .build()
+            .unwrap();
+
+        let deps = mock_dependencies(20, &[]);
+        let addr = permit.validate_versioned(&deps.api, None).unwrap();
+        assert_eq!(
+            addr.as_canonical().unwrap(),
+            bech32_to_canonical(SCOPED_ADDRESS)
+        );
+
+        permit.params.version = 1;
+        permit.signature.signature = Binary::from_base64(V1_SIGNED_TX).unwrap();
+
+        // Correctly signed, but for the old layout
+        assert!(permit.validate(&deps.api, None).is_ok());
+        let err = permit.validate_versioned(&deps.api, None).unwrap_err();
+        assert_eq!(
+            err,
+            PermitError::UnsupportedVersion {
+                version: 1,
+                expected: 2
+            }
+        );
+        assert_eq!(
+            err.to_string(),
+            "Permit params version 1 is not supported, expected 2"
+        );
+    }
+
+    #[test]
+    fn effective_values() {
+        let mut permit = PermitBuilder::new()
+            .params(TestPermitMsg {
+                address: ADDRESS.to_string(),
+                some_number: Uint128(10),
+            })
+            .chain_id("pulsar-1")
+            .sequence(Uint128(5))
+            .signature(PermitSignature::from_base64(PUBKEY, SIGNED_TX).unwrap())
+            .build()
+            .unwrap();
+
+        assert_eq!(permit.effective_chain_id(), "pulsar-1");
+        assert_eq!(permit.effective_sequence(), Uint128(5));
+
+        permit.chain_id = None;
+        permit.sequence = None;
+        assert_eq!(permit.effective_chain_id(), "secret-4");
+        assert_eq!(permit.effective_sequence(), Uint128::zero());
+
+        let signed_tx = permit.create_signed_tx(None);
+        assert_eq!(signed_tx.chain_id, permit.effective_chain_id());
+        assert_eq!(signed_tx.sequence, permit.effective_sequence());
+    }
+
+    #[test]
+    fn signed_fields() {
+        let permit = PermitBuilder::new()
+            .params(TestPermitMsg {
+                address: ADDRESS.to_string(),
+                some_number: Uint128(10),
+            })
+            .chain_id("pulsar-1")
+            .signature(PermitSignature::from_base64(PUBKEY, SIGNED_TX).unwrap())
+            .build()
+            .unwrap();
+
+        let fields: Vec<(String, String)> = vec![
+            ("account_number", "0"),
+            ("chain_id", "pulsar-1"),
+            ("fee.amount.0.amount", "0"),
+            ("fee.amount.0.denom", "uscrt"),
+            ("fee.gas", "1"),
+            ("memo", ""),
+            ("msgs.0.type", "signature_proof"),
+            ("msgs.0.value.address", ADDRESS),
+            ("msgs.0.value.some_number", "10"),
+            ("sequence", "0"),
+        ]
+        .into_iter()
+        .map(|(path, value)| (path.to_string(), value.to_string()))
+        .collect();
+
+        assert_eq!(permit.signed_fields(None).unwrap(), fields);
+    }
+
+    #[test]
+    fn chain_id_prefixes() {
+        assert_eq!(prefix_for_chain_id("secret-4"), Some("secret"));
+        assert_eq!(prefix_for_chain_id("pulsar-2"), Some("secret"));
+        assert_eq!(prefix_for_chain_id("phoenix-1"), Some("terra"));
+        assert_eq!(prefix_for_chain_id("bombay-12"), Some("terra"));
+        assert_eq!(prefix_for_chain_id("osmosis-1"), Some("osmo"));
+        assert_eq!(prefix_for_chain_id("injective-1"), Some("inj"));
+        assert_eq!(prefix_for_chain_id("juno-1"), Some("juno"));
+        assert_eq!(prefix_for_chain_id("cosmoshub-4"), Some("cosmos"));
+        assert_eq!(prefix_for_chain_id("secret"), Some("secret"));
+        assert_eq!(prefix_for_chain_id("unknown-1"), None);
+    }
+
+    #[test]
+    fn chain_id_allowlist() {
+        let permit = PermitBuilder::new()
+            .params(TestPermitMsg {
+                address: ADDRESS.to_string(),
+                some_number: Uint128(10),
+            })
+            .chain_id("pulsar-1")
+            .signature(PermitSignature::from_base64(PUBKEY, SIGNED_TX).unwrap())
+            .build()
+            .unwrap();
+
+        let deps = mock_dependencies(20, &[]);
+        let addr = permit
+            .validate_with_chain_ids(&deps.api, None, &["secret-4", "pulsar-1", "pulsar-2"])
+            .unwrap();
+        assert_eq!(addr.as_canonical().unwrap(), bech32_to_canonical(ADDRESS));
+
+        let err = permit
+            .validate_with_chain_ids(&deps.api, None, &["secret-4", "pulsar-2"])
+            .unwrap_err();
+        assert_eq!(err, PermitError::ChainIdNotAllowed("pulsar-1".to_string()));
+        assert_eq!(err.to_string(), "Chain id pulsar-1 is not allowed");
+        assert!(permit
+            .validate_with_chain_ids(&deps.api, None, &[])
+            .is_err());
+    }
+
+    #[remain::sorted]
+    #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+    #[serde(rename_all = "snake_case")]
+    struct ScopedPermitMsg {
+        pub address: String,
+        pub scopes: Vec<String>,
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Scopes {
+        pub scopes: Vec<String>,
+    }
+
+    const SCOPED_ADDRESS: &str = "secret1wxyx7cgz8pjcnzt0skqwaup8043sxnd7t7u8d8";
+    const SCOPED_PUBKEY: &str = "AimifPuikvLOiLaYvw+By4mBXcPsUVKr/7YWP1I6irWG";
+    const SCOPED_SIGNED_TX: &str =
+        "q3nfufuYw7KEyBbv/5xMCjZa67GvFM58q/rg4pFlrxQNjaOyCu/ymvvNO82LwOBiwr6nSWs7ipM1qv0EzVnHzw==";
+
+    fn scoped_params() -> ScopedPermitMsg {
+        ScopedPermitMsg {
+            address: SCOPED_ADDRESS.to_string(),
+            scopes: vec!["balance".to_string(), "history".to_string()],
+        }
+    }
+
+    /// Builder for the balance and history permit of SCOPED_ADDRESS signed by `signed_tx`
+    fn scoped_builder(signed_tx: &str) -> PermitBuilder<ScopedPermitMsg> {
+        PermitBuilder::new()
+            .params(scoped_params())
+            .signature(PermitSignature::from_base64(SCOPED_PUBKEY, signed_tx).unwrap())
+    }
+
+    fn scoped_permit() -> Permit<ScopedPermitMsg> {
+        scoped_builder(SCOPED_SIGNED_TX).build().unwrap()
+    }
+
+    #[remain::sorted]
+    #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+    #[serde(rename_all = "snake_case")]
+    struct DataPermitMsg {
+        pub address: String,
+        pub data: String,
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct EmptyData {}
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct RequiredData {
+        pub required: String,
+    }
+
+    const DATA_SIGNED_TX: &str =
+        "h2mSIvPqTy9aN516s3GlgVfUFuxrYMGmtwR6v4l0xCxlNwZw8IBA7ZLglkYTTb6iV0bSHyQxapAN1oSjFpSSzw==";
+
+    #[test]
+    fn decode_embedded_data() {
+        let permit = PermitBuilder::new()
+            .params(DataPermitMsg {
+                address: SCOPED_ADDRESS.to_string(),
+                data: "e30=".to_string(),
+            })
+            .signature(PermitSignature::from_base64(SCOPED_PUBKEY, DATA_SIGNED_TX).unwrap())
+            .build()
+            .unwrap();
+
+        let deps = mock_dependencies(20, &[]);
+        let (addr, data): (PubKeyValue, EmptyData) = permit
+            .validate_and_decode(&deps.api, None, |params| &params.data)
+            .unwrap();
+        assert_eq!(
+            addr.as_canonical().unwrap(),
+            bech32_to_canonical(SCOPED_ADDRESS)
+        );
+        assert_eq!(data, EmptyData {});
+
+        // Valid signature, but the data doesn't have the expected structure
+        assert!(permit
+            .validate_and_decode::<_, RequiredData, _>(&deps.api, None, |params| &params.data)
+            .is_err());
+        // Not base64
+        assert!(permit
+            .validate_and_decode::<_, EmptyData, _>(&deps.api, None, |params| &params.address)
+            .is_err());
+    }
+
+    const NO_MEMO_SIGNED_TX: &str =
+        "aczqASp+7Wl7Uipf3UVyGgInswJbc8gzqTaqlBBSOxweeZZbOrJBC6DoRqECYB+9YMHG6Gv4pBhj0g7mVaBumA==";
+
+    #[test]
+    fn omitted_memo() {
+        let mut permit = scoped_builder(NO_MEMO_SIGNED_TX).build().unwrap();
+
+        let deps = mock_dependencies(20, &[]);
+        let addr = permit
+            .validate_omitting_empty_memo(&deps.api, None)
+            .unwrap();
+        assert_eq!(
+            addr.as_canonical().unwrap(),
+            bech32_to_canonical(SCOPED_ADDRESS)
+        );
+
+        permit.memo = Some("".to_string());
+        assert!(permit.validate_omitting_empty_memo(&deps.api, None).is_ok());
+
+        // Regular validation signs "memo":""
+        assert!(!verifies_on_chain(&permit, None));
+        permit.signature.signature = Binary::from_base64(SCOPED_SIGNED_TX).unwrap();
+        assert!(permit.validate(&deps.api, None).is_ok());
+        assert!(verifies_on_chain(&permit, None));
+        assert!(!sign_doc_verifies(
+            &permit.signature,
+            &SigningPayload::from_permit(&permit, None).omit_empty_memo()
+        ));
+    }
+
+    const EXPIRING_SIGNED_TX: &str =
+        "C8NIw9AsQbmTiIcVYL00CPgr62bm5N7KNoO42uro30J5UJUujPNyeKUOOGdr9LWn1JC10kUixU/UTwBkDEo9bQ==";
+
+    #[test]
+    fn permit_expiration() {
+        let mut permit = scoped_builder(EXPIRING_SIGNED_TX)
+            .expires(1000)
+            .build()
+            .unwrap();
+
+        let deps = mock_dependencies(20, &[]);
+        let addr = permit.validate_with_time(&deps.api, None, 999).unwrap();
+        assert_eq!(
+            addr.as_canonical().unwrap(),
+            bech32_to_canonical(SCOPED_ADDRESS)
+        );
+        assert!(permit.validate_with_time(&deps.api, None, 1000).is_ok());
+
+        assert_eq!(
+            permit
+                .validate_with_time(&deps.api, None, 1001)
+                .unwrap_err(),
+            PermitError::Expired
+        );
+
+        // The expiration is signed
+        assert!(verifies_on_chain(&permit, None));
+        permit.expires = Some(2000);
+        assert!(!verifies_on_chain(&permit, None));
+
+        // Permits without expiration keep their old signatures
+        permit.expires = None;
+        permit.signature.signature = Binary::from_base64(SCOPED_SIGNED_TX).unwrap();
+        assert!(permit.validate_with_time(&deps.api, None, u64::MAX).is_ok());
+    }
+
+    #[remain::sorted]
+    #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+    #[serde(rename_all = "snake_case")]
+    struct ResultPermitMsg {
+        pub address: String,
+        pub outcome: Result<Uint128, String>,
+    }
+
+    const OK_SIGNED_TX: &str =
+        "xhLpzYBQqkUQbIK/xM7etfsFHpAPgWCVo2IulOSINWQoq0FF0gwvbffoCYTk7D6e55vP/VrnnSZfwctiCHZbbw==";
+    const ERR_SIGNED_TX: &str =
+        "67ZwgHmaYI4jT7whdjWwNqbuyJBWOGCoVSSuHp/K460LX+hoSPcMk+Xzl3EWFuT6LOrD3434cKjn2kIvLvtoAw==";
+
+    #[test]
+    fn result_params() {
+        let mut permit = PermitBuilder::new()
+            .params(ResultPermitMsg {
+                address: SCOPED_ADDRESS.to_string(),
+                outcome: Ok(Uint128(10)),
+            })
+            .signature(PermitSignature::from_base64(SCOPED_PUBKEY, OK_SIGNED_TX).unwrap())
+            .build()
+            .unwrap();
+
+        // Externally tagged, same as serde_json
+        let value = to_vec(&permit.params).unwrap();
+        assert_eq!(
+            String::from_utf8(value).unwrap(),
+            r#"{"address":"secret1wxyx7cgz8pjcnzt0skqwaup8043sxnd7t7u8d8","outcome":{"Ok":"10"}}"#
+        );
+
+        let deps = mock_dependencies(20, &[]);
+        let addr = permit.validate(&deps.api, None).unwrap();
+        assert_eq!(
+            addr.as_canonical().unwrap(),
+            bech32_to_canonical(SCOPED_ADDRESS)
+        );
+        assert!(permit
+            .signed_fields(None)
+            .unwrap()
+            .contains(&("msgs.0.value.outcome.Ok".to_string(), "10".to_string())));
+
+        assert!(verifies_on_chain(&permit, None));
+
+        permit.params.outcome = Err("failed".to_string());
+        assert!(!verifies_on_chain(&permit, None));
+
+        permit.signature.signature = Binary::from_base64(ERR_SIGNED_TX).unwrap();
+        let addr = permit.validate(&deps.api, None).unwrap();
+        assert_eq!(
+            addr.as_canonical().unwrap(),
+            bech32_to_canonical(SCOPED_ADDRESS)
+        );
+        assert!(permit
+            .signed_fields(None)
+            .unwrap()
+            .contains(&("msgs.0.value.outcome.Err".to_string(), "failed".to_string())));
+    }
+
+    const CODE_HASH: &str = "9a00ca4ad505e9be7e6e6dddf8d939b7ec7e9ac8e109c8681f10db9cacb36d42";
+    const CODE_HASH_SIGNED_TX: &str =
+        "o9ZHLDVN3zS9pdB0cxEtD7Pc3J2YwY6tuUiaa6S8VuA/NCruWQWNo/+V5P59C/SOi8ppeZ6oEEC4xnhrzqQ5bQ==";
+
+    #[test]
+    fn code_hash_binding() {
+        let mut permit = scoped_builder(CODE_HASH_SIGNED_TX)
+            .code_hash(CODE_HASH)
+            .build()
+            .unwrap();
+
+        let deps = mock_dependencies(20, &[]);
+        let addr = permit
+            .validate_for_code_hash(&deps.api, None, CODE_HASH)
+            .unwrap();
+        assert_eq!(
+            addr.as_canonical().unwrap(),
+            bech32_to_canonical(SCOPED_ADDRESS)
+        );
+
+        match permit.validate_for_code_hash(&deps.api, None, "other") {
+            Err(err) => {
+                assert_eq!(err, PermitError::CodeHashMismatch);
+                assert_eq!(
+                    err.to_string(),
+                    "Permit was not signed for this contract code hash"
+                );
+            }
+            Ok(_) => panic!("permit for another code hash was accepted"),
+        }
+
+        // Same signature claimed for another code hash
+        assert!(verifies_on_chain(&permit, None));
+        permit.code_hash = Some("other".to_string());
+        assert!(!verifies_on_chain(&permit, None));
+
+        permit.code_hash = None;
+        assert!(permit
+            .validate_for_code_hash(&deps.api, None, CODE_HASH)
+            .is_err());
+    }
+
+    const DEVICE_PUBKEY: &str = "AwiGK227dhvrny8c6C3b34CJrflwcliAbZ6ERvWenmvN";
+    const DEVICE_COSIGN: &str =
+        "ZJ8niBiVWa30BfSlOntTVTXfsCZ6o+UGXFHubFY+TcVkrXqX+BW4aGx9QnKMG3qXqzP/PfkJE4XLAYp3jj+J8w==";
+    // Device signature over a sign-doc with other scopes
+    const DEVICE_OTHER_COSIGN: &str =
+        "6xx0/2qaRVCu4vLqsMhczV7MfU0rst7T6fVGBCA6fV0ieQn39AJaIZGwgL+WW67FNQALOk/q9ot9M5kINOgF0w==";
+
+    #[test]
+    fn device_cosign() {
+        let permit = scoped_permit();
+
+        let deps = mock_dependencies(20, &[]);
+        let signer = permit.validate(&deps.api, None).unwrap();
+        let hash = sha_256(
+            &SigningPayload::from_permit(&permit, None)
+                .to_bytes()
+                .unwrap(),
+        );
+        let device = Binary::from_base64(DEVICE_PUBKEY).unwrap();
+
+        assert!(signer
+            .verify_cosign(
+                &deps.api,
+                &hash,
+                &Binary::from_base64(DEVICE_COSIGN).unwrap(),
+                &device
+            )
+            .unwrap());
+        assert!(secp256k1_verifies(
+            &hash,
+            &Binary::from_base64(DEVICE_COSIGN).unwrap(),
+            &device
+        ));
+        assert!(!secp256k1_verifies(
+            &hash,
+            &Binary::from_base64(DEVICE_OTHER_COSIGN).unwrap(),
+            &device
+        ));
+
+        // The permit signature doesn't count as its own co-signature
+        assert!(signer
+            .verify_cosign(
+                &deps.api,
+                &hash,
+                &permit.signature.signature,
+                &permit.signature.pub_key.value
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn der_signature() {
+        // SCOPED_SIGNED_TX in DER, and with S replaced by n - S
+        let der = "MEUCIQCred+5+5jDsoTIFu//nEwKNlrrsa8Uznyr+uDikWWvFAIgDY2jsgrv8pr7zTvNi8DgYsK+p0lrO4qTNar9BM1Zx88=";
+        let high_s = "MEYCIQCred+5+5jDsoTIFu//nEwKNlrrsa8Uznyr+uDikWWvFAIhAPJyXE31EA1lBDLEMnQ/H5v38DWdRA0VqIonYYgC3Hly";
+        let pub_key = PubKey::new(Binary::from_base64(SCOPED_PUBKEY).unwrap());
+
+        for encoded in [der, high_s].iter() {
+            let signature = PermitSignature::from_der(
+                pub_key.clone(),
+                Binary::from_base64(encoded).unwrap().as_slice(),
+            )
+            .unwrap();
+            assert_eq!(signature.signature.to_base64(), SCOPED_SIGNED_TX);
+
+            let permit = PermitBuilder::new()
+                .params(scoped_params())
+                .signature(signature)
+                .build()
+                .unwrap();
+            let deps = mock_dependencies(20, &[]);
+            assert!(permit.validate(&deps.api, None).is_ok());
+        }
+
+        assert!(PermitSignature::from_der(pub_key, &[0x30, 0x02, 0x02, 0x00]).is_err());
+    }
+
+    #[test]
+    fn batch_validation() {
+        let scoped = scoped_permit();
+        let mut tampered = scoped.clone();
+        tampered.params.scopes.pop();
+        assert!(!verifies_on_chain(&tampered, None));
+        tampered.signature.pub_key.value = Binary(vec![4; 33]);
+
+        let deps = mock_dependencies(20, &[]);
+        let signers =
+            Permit::validate_batch(&deps.api, &[scoped.clone(), scoped.clone()], None).unwrap();
+        assert_eq!(signers.len(), 2);
+        assert_eq!(
+            signers[1].as_canonical().unwrap(),
+            bech32_to_canonical(SCOPED_ADDRESS)
+        );
+
+        assert!(matches!(
+            Permit::validate_batch(&deps.api, &[scoped.clone(), tampered, scoped], None)
+                .unwrap_err(),
+            StdError::GenericErr { msg, .. } if msg.starts_with("Permit at index 1 is invalid: Invalid pubkey")
+        ));
+        assert_eq!(
+            Permit::<ScopedPermitMsg>::validate_batch(&deps.api, &[], None).unwrap(),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn many_signatures() {
+        let params = scoped_params();
+        let hash = sha_256(
+            &SigningPayload::new(
+                &params,
+                "secret-4",
+                Uint128::zero(),
+                Uint128::zero(),
+                "",
+                None,
+            )
+            .to_bytes()
+            .unwrap(),
+        );
+
+        let pairs = [
+            (
+                Binary::from_base64(SCOPED_SIGNED_TX).unwrap(),
+                Binary::from_base64(SCOPED_PUBKEY).unwrap(),
+            ),
+            (
+                Binary::from_base64(DEVICE_OTHER_COSIGN).unwrap(),
+                Binary::from_base64(DEVICE_PUBKEY).unwrap(),
+            ),
+            (
+                Binary::from_base64(DEVICE_COSIGN).unwrap(),
+                Binary::from_base64(DEVICE_PUBKEY).unwrap(),
+            ),
+        ];
+
+        let deps = mock_dependencies(20, &[]);
+        assert_eq!(verify_many(&deps.api, &hash, &pairs).len(), pairs.len());
+
+        let on_chain: Vec<bool> = pairs
+            .iter()
+            .map(|(signature, pubkey)| secp256k1_verifies(&hash, signature, pubkey))
+            .collect();
+        assert_eq!(on_chain, vec![true, false, true]);
+    }
+
+    const ACTION_SIGNED_TX: &str =
+        "YHcsEFcMlu5tyitANlQsjJlLYxv5nUC+LVV2XyirX5BfbkYVSuEl086q2x12W0rtVtQFqwoijwFG/z3C0h6taQ==";
+
+    #[test]
+    fn action_binding() {
+        let mut permit = scoped_builder(ACTION_SIGNED_TX)
+            .action("query_balance")
+            .build()
+            .unwrap();
+
+        let deps = mock_dependencies(20, &[]);
+        let addr = permit
+            .validate_for_action(&deps.api, None, "query_balance")
+            .unwrap();
+        assert_eq!(
+            addr.as_canonical().unwrap(),
+            bech32_to_canonical(SCOPED_ADDRESS)
+        );
+
+        match permit.validate_for_action(&deps.api, None, "query_history") {
+            Err(err) => {
+                assert_eq!(
+                    err,
+                    PermitError::ActionMismatch("query_history".to_string())
+                );
+                assert_eq!(
+                    err.to_string(),
+                    "Permit was not signed for action query_history"
+                );
+            }
+            Ok(_) => panic!("permit for another action was accepted"),
+        }
+
+        // Claiming another action breaks the signature
+        assert!(verifies_on_chain(&permit, None));
+        permit.action = Some("query_history".to_string());
+        assert!(!verifies_on_chain(&permit, None));
+    }
+
+    const ED25519_ADDRESS: &str = "secret18lmw4deaemr9dc4m7g490ujazmmwegu78lyrw6";
+    const ED25519_PUBKEY: &str = "nTtJB6dHKhY19lHq6IUk09APbk8xPI6PZTBPd0IUqdQ=";
+    const ED25519_SIGNED_TX: &str =
+        "yf7J/GQ4FH/MxE0Ou3aCqE1XG/U4NEp/7Ar4+mZUCaalccDc5SfPpgBZryu3DMlT68nFkhPvf+1tSZqFZziXAA==";
+
+    #[test]
+    fn ed25519_pubkey() {
+        let mut permit = PermitBuilder::new()
+            .params(ScopedPermitMsg {
+                address: ED25519_ADDRESS.to_string(),
+                scopes: vec!["balance".to_string()],
+            })
+            .signature(PermitSignature {
+                pub_key: PubKey {
+                    r#type: ED25519_TYPE.to_string(),
+                    value: Binary::from_base64(ED25519_PUBKEY).unwrap(),
+                },
+                signature: Binary::from_base64(ED25519_SIGNED_TX).unwrap(),
+            })
+            .build()
+            .unwrap();
+
+        let deps = mock_dependencies(20, &[]);
+        assert_eq!(
+            permit.validate_canonical(&deps.api, None).unwrap(),
+            bech32_to_canonical(ED25519_ADDRESS)
+        );
+        assert!(permit
+            .validate(&deps.api, None)
+            .unwrap()
+            .matches(&bech32_to_canonical(ED25519_ADDRESS)));
+        assert!(permit
+            .validate_signer_field(&deps.api, None, |params| &params.address)
+            .is_ok());
+
+        assert_eq!(
+            permit
+                .validate(&deps.api, None)
+                .unwrap()
+                .as_humanaddr(None)
+                .unwrap(),
+            HumanAddr(ED25519_ADDRESS.to_string())
+        );
+
+        // The mock api accepts any ed25519 signature, check the fixture against the sign-doc hash
+        let hash = sha_256(&permit.signed_bytes(None).unwrap());
+        assert!(ed25519_verifies(
+            &hash,
+            &permit.signature.signature,
+            &permit.signature.pub_key.value
+        ));
+
+        // Changing the params changes what's verified
+        permit.params.scopes.push("history".to_string());
+        let hash = sha_256(&permit.signed_bytes(None).unwrap());
+        assert!(!ed25519_verifies(
+            &hash,
+            &permit.signature.signature,
+            &permit.signature.pub_key.value
+        ));
+    }
+
+    #[test]
+    fn signature_from_base64() {
+        assert_eq!(
+            PermitSignature::from_base64(SCOPED_PUBKEY, SCOPED_SIGNED_TX).unwrap(),
+            PermitSignature {
+                pub_key: PubKey::new(Binary::from_base64(SCOPED_PUBKEY).unwrap()),
+                signature: Binary::from_base64(SCOPED_SIGNED_TX).unwrap(),
+            }
+        );
+        assert!(PermitSignature::from_base64("not base64!", SCOPED_SIGNED_TX).is_err());
+        assert!(PermitSignature::from_base64(SCOPED_PUBKEY, "not base64!").is_err());
+    }
+
+    #[test]
+    fn verify_claimed_address() {
+        let permit = scoped_permit();
+        assert!(verifies_on_chain(&permit, None));
+
+        let deps = mock_dependencies(20, &[]);
+        assert!(permit
+            .verify_address(&deps.api, None, &HumanAddr(SCOPED_ADDRESS.to_string()))
+            .is_ok());
+
+        // Same account on another chain
+        let cosmos_address = bech32::encode(
+            "cosmos",
+            bech32_to_canonical(SCOPED_ADDRESS).as_slice().to_base32(),
+            Variant::Bech32,
+        )
+        .unwrap();
+        assert!(permit
+            .verify_address(&deps.api, None, &HumanAddr(cosmos_address))
+            .is_ok());
+
+        assert_eq!(
+            permit
+                .verify_address(&deps.api, None, &HumanAddr(ED25519_ADDRESS.to_string()))
+                .unwrap_err(),
+            StdError::generic_err("Permit was not signed by the claimed address")
+        );
+        assert!(permit
+            .verify_address(&deps.api, None, &HumanAddr("not an address".to_string()))
+            .is_err());
+    }
+
+    #[test]
+    fn strict_chain_id() {
+        let mut permit = scoped_permit();
+
+        let deps = mock_dependencies(20, &[]);
+        assert!(permit.validate(&deps.api, None).is_ok());
+        match permit.validate_strict(&deps.api, None) {
+            Err(err) => {
+                assert_eq!(err, PermitError::MissingChainId);
+                assert_eq!(err.to_string(), "Permit must specify a chain id");
+            }
+            Ok(_) => panic!("missing chain id was accepted"),
+        }
+
+        permit.chain_id = Some("secret-4".to_string());
+        let addr = permit.validate_strict(&deps.api, None).unwrap();
+        assert_eq!(
+            addr.as_canonical().unwrap(),
+            bech32_to_canonical(SCOPED_ADDRESS)
+        );
+    }
+
+    #[test]
+    fn expected_chain_id() {
+        let permit = PermitBuilder::new()
+            .params(TestPermitMsg {
+                address: ADDRESS.to_string(),
+                some_number: Uint128(10),
+            })
+            .chain_id("pulsar-1")
+            .signature(PermitSignature::from_base64(PUBKEY, SIGNED_TX).unwrap())
+            .build()
+            .unwrap();
+
+        let deps = mock_dependencies(20, &[]);
+        let addr = permit
+            .validate_on_chain(&deps.api, None, "pulsar-1")
+            .unwrap();
+        assert_eq!(addr.as_canonical().unwrap(), bech32_to_canonical(ADDRESS));
+
+        let err = permit
+            .validate_on_chain(&deps.api, None, "secret-4")
+            .unwrap_err();
+        assert_eq!(err, PermitError::ChainIdMismatch("secret-4".to_string()));
+        assert_eq!(
+            StdError::from(err),
+            StdError::generic_err("Permit was not signed for chain id secret-4")
+        );
+
+        // Signed for secret-4 through the default, but never named it
+        let permit = scoped_permit();
+        assert_eq!(permit.chain_id, None);
+        assert_eq!(permit.effective_chain_id(), "secret-4");
+        assert!(verifies_on_chain(&permit, None));
+        assert_eq!(
+            permit
+                .validate_on_chain(&deps.api, None, "secret-4")
+                .unwrap_err(),
+            PermitError::ChainIdMismatch("secret-4".to_string())
+        );
+    }
+
+    // Private key 1, signer of the fixtures below
+    const KEY_ONE_ADDRESS: &str = "secret1w508d6qejxtdg4y5r3zarvary0c5xw7kccrnjy";
+    const KEY_ONE_PUBKEY: &str = "Anm+Zn753LusVaBilc6HCwcCm/zbLc4o2VnygVsW+BeY";
+    // Signed at sequence 5
+    const SEQUENCE_SIGNED_TX: &str =
+        "WzbOKbKJRkiNWTTV1H5hIjitUvBVaZiwCTqzDKxZ0OsF1Uh88nxLi7ZmQ88+3iFhSqN71RFe8boaK7GT5u9s/g==";
+
+    #[test]
+    fn sequence_replay() {
+        let mut permit = PermitBuilder::new()
+            .params(TestPermitMsg {
+                address: KEY_ONE_ADDRESS.to_string(),
+                some_number: Uint128(10),
+            })
+            .chain_id("pulsar-1")
+            .sequence(Uint128(5))
+            .signature(PermitSignature::from_base64(KEY_ONE_PUBKEY, SEQUENCE_SIGNED_TX).unwrap())
+            .build()
+            .unwrap();
+        assert!(verifies_on_chain(&permit, None));
+
+        let deps = mock_dependencies(20, &[]);
+        let (addr, sequence) = permit
+            .validate_with_sequence(&deps.api, None, Uint128(4))
+            .unwrap();
+        assert_eq!(
+            addr.as_canonical().unwrap(),
+            bech32_to_canonical(KEY_ONE_ADDRESS)
+        );
+        assert_eq!(sequence, Uint128(5));
+
+        for last_seen in [Uint128(5), Uint128(6)].iter() {
+            assert_eq!(
+                permit
+                    .validate_with_sequence(&deps.api, None, *last_seen)
+                    .unwrap_err(),
+                PermitError::StaleSequence(*last_seen)
+            );
+        }
+
+        // A missing sequence signs 0, which is never past anything
+        permit.sequence = None;
+        assert!(!verifies_on_chain(&permit, None));
+        assert_eq!(
+            StdError::from(
+                permit
+                    .validate_with_sequence(&deps.api, None, Uint128::zero())
+                    .unwrap_err()
+            ),
+            StdError::generic_err("Permit sequence must be greater than 0")
+        );
+    }
+
+    #[test]
+    fn sign_doc_bytes() {
+        let permit = scoped_permit();
+
+        assert_eq!(
+            String::from_utf8(permit.signed_bytes(None).unwrap()).unwrap(),
+            concat!(
+                r#"{"account_number":"0","chain_id":"secret-4","#,
+                r#""fee":{"amount":[{"amount":"0","denom":"uscrt"}],"gas":"1"},"memo":"","#,
+                r#""msgs":[{"type":"signature_proof","value":{"#,
+                r#""address":"secret1wxyx7cgz8pjcnzt0skqwaup8043sxnd7t7u8d8","#,
+                r#""scopes":["balance","history"]}}],"sequence":"0"}"#
+            )
+        );
+
+        let signed_bytes = permit.signed_bytes(None).unwrap();
+        let eth_bytes = permit.eth_signed_bytes(None).unwrap();
+        let prefix = format!("\x19Ethereum Signed Message:\n{}", signed_bytes.len());
+        assert!(eth_bytes.starts_with(prefix.as_bytes()));
+        assert_eq!(&eth_bytes[prefix.len()..], signed_bytes.as_slice());
+    }
+
+    // Two messages scoped to balance and history
+    const TWO_MSGS_SIGNED_TX: &str =
+        "DgubldoCpUSIZVFbZ8DCEM42wUCiPHXI1aaGKeVFi8Mjs/DknDM8zZW0H1tpzPMVFvHmNq6oQZGQzOmhhmgcYg==";
+
+    #[test]
+    fn multiple_messages() {
+        let permit = Permit::from_multi(
+            vec![key_one_scopes(&["balance"]), key_one_scopes(&["history"])],
+            PermitSignature::from_base64(KEY_ONE_PUBKEY, TWO_MSGS_SIGNED_TX).unwrap(),
+        );
+
+        let signed_bytes = permit.signed_bytes_multi(None).unwrap();
+        assert_eq!(
+            String::from_utf8(signed_bytes.clone()).unwrap(),
+            concat!(
+                r#"{"account_number":"0","chain_id":"secret-4","#,
+                r#""fee":{"amount":[{"amount":"0","denom":"uscrt"}],"gas":"1"},"memo":"","#,
+                r#""msgs":[{"type":"signature_proof","value":{"#,
+                r#""address":"secret1w508d6qejxtdg4y5r3zarvary0c5xw7kccrnjy","scopes":["balance"]}},"#,
+                r#"{"type":"signature_proof","value":{"#,
+                r#""address":"secret1w508d6qejxtdg4y5r3zarvary0c5xw7kccrnjy","scopes":["history"]}}],"#,
+                r#""sequence":"0"}"#
+            )
+        );
+        assert_eq!(
+            to_vec(&SignedTx::from_permit_multi(&permit, None)).unwrap(),
+            signed_bytes
+        );
+        assert_ne!(permit.signed_bytes(None).unwrap(), signed_bytes);
+        assert!(sign_doc_verifies(
+            &permit.signature,
+            &SignedTxRef::from_permit_multi(&permit, None)
+        ));
+
+        let deps = mock_dependencies(20, &[]);
+        let addr = permit.validate_multi(&deps.api, None).unwrap();
+        assert_eq!(
+            addr.as_canonical().unwrap(),
+            bech32_to_canonical(KEY_ONE_ADDRESS)
+        );
+
+        // Swapping the messages changes the signed bytes
+        let mut swapped = permit;
+        swapped.params.swap(0, 1);
+        assert!(!sign_doc_verifies(
+            &swapped.signature,
+            &SignedTxRef::from_permit_multi(&swapped, None)
+        ));
+    }
+
+    // Three messages scoped to balance, history and allowance
+    const THREE_MSGS_SIGNED_TX: &str =
+        "d48i7YlrC+KoNHN8MKP39j3p6Uz+9BCKLz7f0dfYgYwfLtxDW8RFrzqG+psHDyBcfcl6lfpLDY8bdDd6YBzRVA==";
+
+    fn key_one_scopes(scopes: &[&str]) -> ScopedPermitMsg {
+        ScopedPermitMsg {
+            address: KEY_ONE_ADDRESS.to_string(),
+            scopes: scopes.iter().map(|scope| scope.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn message_at_index() {
+        let permit = Permit::from_multi(
+            vec![
+                key_one_scopes(&["balance"]),
+                key_one_scopes(&["history"]),
+                key_one_scopes(&["allowance"]),
+            ],
+            PermitSignature::from_base64(KEY_ONE_PUBKEY, THREE_MSGS_SIGNED_TX).unwrap(),
+        );
+        assert!(sign_doc_verifies(
+            &permit.signature,
+            &SignedTxRef::from_permit_multi(&permit, None)
+        ));
+
+        let deps = mock_dependencies(20, &[]);
+        let addr = permit
+            .validate_msg_at(&deps.api, None, 1, &key_one_scopes(&["history"]))
+            .unwrap();
+        assert_eq!(
+            addr.as_canonical().unwrap(),
+            bech32_to_canonical(KEY_ONE_ADDRESS)
+        );
+
+        assert_eq!(
+            permit
+                .validate_msg_at(&deps.api, None, 1, &key_one_scopes(&["balance"]))
+                .unwrap_err(),
+            PermitError::MessageMismatch(1)
+        );
+        assert_eq!(
+            permit
+                .validate_msg_at(&deps.api, None, 3, &key_one_scopes(&["history"]))
+                .unwrap_err(),
+            PermitError::MessageMismatch(3)
+        );
+
+        // Dropping a message the contract doesn't care about still breaks the signature
+        let mut partial = permit.clone();
+        partial.params.remove(2);
+        assert!(!sign_doc_verifies(
+            &partial.signature,
+            &SignedTxRef::from_permit_multi(&partial, None)
+        ));
     }
 
-    /// Returns the permit signer
-    pub fn validate<A: Api>(&self, api: &A, msg_type: Option<String>) -> StdResult<PubKeyValue> {
-        Permit::validate_signed_tx(api, &self.signature, &self.create_signed_tx(msg_type))
+    // Signed with a 5000uscrt fee and 200000 gas
+    const FEE_SIGNED_TX: &str =
+        "w1CKFUH+DeQEjRCb2/0rEPBp/3J6OTv4YlPl2MfBPFMMbnIvCxmzSmpN0ThMl6JPL+MxKdUNKZdqPe209VZGAw==";
+
+    #[test]
+    fn custom_fee() {
+        let mut permit = scoped_permit();
+        let default_bytes = permit.signed_bytes(None).unwrap();
+
+        // Spelling out the default fee keeps the signature
+        permit.fee = Some(Fee::new(
+            vec![Coin::new("uscrt", Uint128::zero())],
+            Uint128(1),
+        ));
+        assert_eq!(permit.signed_bytes(None).unwrap(), default_bytes);
+
+        let fee = Fee::new(vec![Coin::new("uscrt", Uint128(5000))], Uint128(200000));
+        permit.fee = Some(fee.clone());
+        let signed_bytes = String::from_utf8(permit.signed_bytes(None).unwrap()).unwrap();
+        assert!(signed_bytes
+            .contains(r#""fee":{"amount":[{"amount":"5000","denom":"uscrt"}],"gas":"200000"}"#));
+        assert_eq!(
+            signed_bytes.as_bytes(),
+            SigningPayload::new(
+                &permit.params,
+                "secret-4",
+                Uint128::zero(),
+                Uint128::zero(),
+                "",
+                None
+            )
+            .with_fee(fee.clone())
+            .to_bytes()
+            .unwrap()
+        );
+
+        // Only verifies with the fee it was signed with
+        let permit = PermitBuilder::new()
+            .params(key_one_scopes(&["balance", "history"]))
+            .fee(fee)
+            .signature(PermitSignature::from_base64(KEY_ONE_PUBKEY, FEE_SIGNED_TX).unwrap())
+            .build()
+            .unwrap();
+        assert!(verifies_on_chain(&permit, None));
+
+        let deps = mock_dependencies(20, &[]);
+        let addr = permit.validate(&deps.api, None).unwrap();
+        assert_eq!(
+            addr.as_canonical().unwrap(),
+            bech32_to_canonical(KEY_ONE_ADDRESS)
+        );
+
+        let mut default_fee = permit;
+        default_fee.fee = None;
+        assert!(!verifies_on_chain(&default_fee, None));
     }
 
-    pub fn validate_signed_tx<A: Api>(
-        api: &A,
-        signature: &PermitSignature,
-        signed_tx: &SignedTx<T>,
-    ) -> StdResult<PubKeyValue> {
-        let pubkey = &signature.pub_key.value;
+    crate::permit_params! {
+        struct Window {
+            end: u64,
+            start: u64,
+        }
+    }
 
-        // Validate signature
-        let signed_bytes = to_binary(signed_tx)?;
-        let signed_bytes_hash = sha_256(signed_bytes.as_slice());
+    crate::permit_params! {
+        struct Limit {
+            amount: Uint128,
+            window: Window,
+        }
+    }
 
-        let verified = api
-            .secp256k1_verify(&signed_bytes_hash, &signature.signature.0, &pubkey.0)
-            .map_err(|err| StdError::generic_err(err.to_string()))?;
+    crate::permit_params! {
+        struct Allowance {
+            limits: Vec<Limit>,
+            spender: String,
+        }
+    }
 
-        if !verified {
-            return Err(StdError::generic_err("Signature verification failed"));
+    crate::permit_params! {
+        struct NestedPermitMsg {
+            allowances: Vec<Allowance>,
         }
+    }
+
+    #[test]
+    fn nested_sign_doc_bytes() {
+        let limit = |amount, start, end| Limit {
+            amount: Uint128(amount),
+            window: Window { end, start },
+        };
+        let permit = PermitBuilder::new()
+            .params(NestedPermitMsg {
+                allowances: vec![
+                    Allowance {
+                        limits: vec![limit(100, 0, 10), limit(5, 10, 20)],
+                        spender: SCOPED_ADDRESS.to_string(),
+                    },
+                    Allowance {
+                        limits: vec![],
+                        spender: ADDRESS.to_string(),
+                    },
+                ],
+            })
+            .signature(PermitSignature::from_base64(SCOPED_PUBKEY, SCOPED_SIGNED_TX).unwrap())
+            .build()
+            .unwrap();
 
-        Ok(PubKeyValue(pubkey.clone()))
+        assert_eq!(
+            String::from_utf8(permit.signed_bytes(None).unwrap()).unwrap(),
+            concat!(
+                r#"{"account_number":"0","chain_id":"secret-4","#,
+                r#""fee":{"amount":[{"amount":"0","denom":"uscrt"}],"gas":"1"},"memo":"","#,
+                r#""msgs":[{"type":"signature_proof","value":{"allowances":["#,
+                r#"{"limits":[{"amount":"100","window":{"end":10,"start":0}},"#,
+                r#"{"amount":"5","window":{"end":20,"start":10}}],"#,
+                r#""spender":"secret1wxyx7cgz8pjcnzt0skqwaup8043sxnd7t7u8d8"},"#,
+                r#"{"limits":[],"spender":"secret102nasmxnxvwp5agc4lp3flc6s23335xm8g7gn9"}"#,
+                r#"]}}],"sequence":"0"}"#
+            )
+        );
     }
-}
 
-#[cfg(test)]
-mod signature_tests {
-    use super::*;
-    use crate::transaction::PubKey;
-    use cosmwasm_std::testing::mock_dependencies;
-    use cosmwasm_std::{HumanAddr, Uint128};
+    #[test]
+    fn signing_payload_round_trip() {
+        let params = scoped_params();
+
+        // What a client signs
+        let payload = SigningPayload::new(
+            &params,
+            "secret-4",
+            Uint128::zero(),
+            Uint128::zero(),
+            "",
+            None,
+        );
+        let pubkey = Binary::from_base64(SCOPED_PUBKEY).unwrap();
+        let signature = Binary::from_base64(SCOPED_SIGNED_TX).unwrap();
+
+        let deps = mock_dependencies(20, &[]);
+        assert!(deps
+            .api
+            .secp256k1_verify(
+                &sha_256(&payload.to_bytes().unwrap()),
+                signature.as_slice(),
+                pubkey.as_slice()
+            )
+            .unwrap());
+
+        // What the contract validates
+        let permit = PermitBuilder::new()
+            .params(params.clone())
+            .signature(PermitSignature {
+                pub_key: PubKey::new(pubkey),
+                signature,
+            })
+            .build()
+            .unwrap();
+        assert_eq!(SigningPayload::from_permit(&permit, None), payload);
+        let addr = permit.validate(&deps.api, None).unwrap();
+        assert_eq!(
+            addr.as_canonical().unwrap(),
+            bech32_to_canonical(SCOPED_ADDRESS)
+        );
+    }
+
+    #[test]
+    fn validate_into_scopes() {
+        let permit = scoped_permit();
+
+        let deps = mock_dependencies(20, &[]);
+        let (addr, scopes): (PubKeyValue, Scopes) = permit.validate_into(&deps.api, None).unwrap();
+
+        assert_eq!(
+            addr.as_canonical().unwrap(),
+            bech32_to_canonical(SCOPED_ADDRESS)
+        );
+        assert_eq!(
+            scopes,
+            Scopes {
+                scopes: vec!["balance".to_string(), "history".to_string()]
+            }
+        );
+    }
 
-    #[remain::sorted]
     #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+    struct Point(u32, u32);
+
+    #[remain::sorted]
+    #[derive(Serialize, Clone, Debug, PartialEq)]
+    struct PointMsg {
+        address: String,
+        point: Point,
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct PointOnly {
+        point: Point,
+    }
+
+    #[test]
+    fn validate_into_tuple_struct() {
+        let permit = PermitBuilder::new()
+            .params(PointMsg {
+                address: SCOPED_ADDRESS.to_string(),
+                point: Point(1, 2),
+            })
+            .signature(PermitSignature::from_base64(SCOPED_PUBKEY, SCOPED_SIGNED_TX).unwrap())
+            .build()
+            .unwrap();
+
+        // Tuple structs are written as arrays, same as the sign-doc
+        let deps = mock_dependencies(20, &[]);
+        let (_, params): (PubKeyValue, PointOnly) = permit.validate_into(&deps.api, None).unwrap();
+        assert_eq!(params, PointOnly { point: Point(1, 2) });
+    }
+
+    const COMMITTED_PARAMS: &str = "JupsEKiT0GmK4MVIeQcHrJwkDUI+LHU5MSYvexA/1Pk=";
+    const COMMITTED_SIGNED_TX: &str =
+        "/df2xHE3tiAaNbAmAfRrn0asoHrAzJB8rs16F5PCWMFR9uvIGV4e41GiJ7ahY2acpb7bwSf1RvTCNlYUoaYSEw==";
+
+    #[test]
+    fn committed_params() {
+        let permit = PermitBuilder::new()
+            .params(Binary::from_base64(COMMITTED_PARAMS).unwrap())
+            .signature(PermitSignature::from_base64(SCOPED_PUBKEY, COMMITTED_SIGNED_TX).unwrap())
+            .build()
+            .unwrap();
+
+        let mut full_params = TestPermitMsg {
+            address: SCOPED_ADDRESS.to_string(),
+            some_number: Uint128(10),
+        };
+
+        let deps = mock_dependencies(20, &[]);
+        let addr = permit
+            .validate_committed(&deps.api, None, &full_params)
+            .unwrap();
+        assert_eq!(
+            addr.as_canonical().unwrap(),
+            bech32_to_canonical(SCOPED_ADDRESS)
+        );
+
+        full_params.some_number = Uint128(11);
+        match permit.validate_committed(&deps.api, None, &full_params) {
+            Err(err) => assert_eq!(
+                err,
+                StdError::generic_err("Params do not match the signed commitment")
+            ),
+            Ok(_) => panic!("tampered params were accepted"),
+        }
+
+        // Hashed with the sign-doc serializer, which takes tuple structs
+        assert!(permit
+            .validate_committed(&deps.api, None, &Point(1, 2))
+            .is_err());
+    }
+
+    #[remain::sorted]
+    #[derive(Serialize, Debug, PartialEq)]
     #[serde(rename_all = "snake_case")]
-    struct TestPermitMsg {
+    struct NoCloneMsg {
         pub address: String,
         pub some_number: Uint128,
     }
 
-    type TestPermit = Permit<TestPermitMsg>;
+    #[test]
+    fn no_clone_params() {
+        let permit = PermitBuilder::new()
+            .params(NoCloneMsg {
+                address: ADDRESS.to_string(),
+                some_number: Uint128(10),
+            })
+            .chain_id("pulsar-1")
+            .signature(PermitSignature::from_base64(PUBKEY, SIGNED_TX).unwrap())
+            .build()
+            .unwrap();
 
-    const ADDRESS: &str = "secret102nasmxnxvwp5agc4lp3flc6s23335xm8g7gn9";
-    const PUBKEY: &str = "A0qzJ3s16OKUfn1KFyh533vBnBOQIT0jm+R/FBobJCfa";
-    const SIGNED_TX: &str =
-        "4pZtghyHKHHmwiGNC5JD8JxCJiO+44j6GqaLPc19Q7lt85tr0IRZHYcnc0pkokIds8otxU9rcuvPXb0+etLyVA==";
+        let deps = mock_dependencies(20, &[]);
+        let addr = permit.validate(&deps.api, None).unwrap();
+        assert_eq!(addr.as_canonical().unwrap(), bech32_to_canonical(ADDRESS));
+    }
 
-    // Use secretcli tx sign-doc file --from account
-    //{
-    //  "account_number": "0",
-    //  "chain_id": "pulsar-1",
-    //  "fee": {
-    //      "amount": [{
-    //          "amount": "0",
-    //          "denom": "uscrt"
-    //      }],
-    //      "gas": "1"
-    //  },
-    //  "memo": "",
-    //  "msgs": [{
-    //      "type": "signature_proof",
-    //      "value": {
-    //          "address": "secret102nasmxnxvwp5agc4lp3flc6s23335xm8g7gn9",
-    //          "some_number": "10"
-    //      }
-    //  }],
-    //  "sequence": "0"
-    // }
+    #[derive(Serialize, Debug, PartialEq)]
+    #[serde(rename_all = "snake_case")]
+    struct UnsortedMsg {
+        pub some_number: Uint128,
+        pub address: String,
+    }
 
     #[test]
-    fn test_signed_tx() {
-        let mut permit = TestPermit {
-            params: TestPermitMsg {
+    fn unsorted_params() {
+        let permit = PermitBuilder::new()
+            .params(UnsortedMsg {
                 address: ADDRESS.to_string(),
                 some_number: Uint128(10),
-            },
-            chain_id: Some("pulsar-1".to_string()),
-            sequence: None,
-            signature: PermitSignature {
-                pub_key: PubKey::new(Binary::from_base64(PUBKEY).unwrap()),
-                signature: Binary::from_base64(SIGNED_TX).unwrap(),
-            },
-            account_number: None,
-            memo: None,
-        };
+            })
+            .chain_id("pulsar-1")
+            .signature(PermitSignature::from_base64(PUBKEY, SIGNED_TX).unwrap())
+            .build()
+            .unwrap();
 
-        let deps = mock_dependencies(20, &[]);
-        let addr = permit.validate(&deps.api, None).unwrap();
+        // Same fields as the signed params, but in another order
+        assert!(!verifies_on_chain(&permit, None));
         assert_eq!(
-            addr.as_humanaddr(None).unwrap(),
-            HumanAddr(ADDRESS.to_string())
+            compact::assert_sorted_fields(&SignedTxRef::from_permit(&permit, None)).unwrap_err(),
+            StdError::serialize_err(
+                std::any::type_name::<SignedTxRef<UnsortedMsg>>(),
+                "Field address is out of order, it must come before some_number"
+            )
         );
-        assert_eq!(addr.as_canonical(), bech32_to_canonical(ADDRESS));
+    }
 
-        permit.params.some_number = Uint128(100);
-        // NOTE: SN mock deps dont have a valid working implementation of the dep functons for some reason
-        //assert!(permit.validate(&deps.api, None).is_err());
+    #[test]
+    fn signed_tx_ref_bytes() {
+        let permit = PermitBuilder::new()
+            .params(TestPermitMsg {
+                address: ADDRESS.to_string(),
+                some_number: Uint128(10),
+            })
+            .chain_id("pulsar-1")
+            .signature(PermitSignature::from_base64(PUBKEY, SIGNED_TX).unwrap())
+            .memo("memo")
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            to_binary(&permit.create_signed_tx(None)).unwrap(),
+            to_binary(&SignedTxRef::from_permit(&permit, None)).unwrap()
+        );
     }
 
     const FILLERPERMITNAME: &str = "wasm/MsgExecuteContract";
@@ -152,6 +3103,117 @@ mod signature_tests {
     #[serde(rename_all = "snake_case")]
     struct EmptyMsg {}
 
+    const SENDER_SIGNED_TX: &str =
+        "/nLbyWizQXiH8f8wF7oOEt+Kf7EWQceUOB56A/NUyF9JHh/0hTj8cClh0cXsGQo3X4Hmmusgrms4+WRGzKM/rQ==";
+    // Same signer, but the params claim ADDRESS as the sender
+    const OTHER_SENDER_SIGNED_TX: &str =
+        "sqIsmRd8nES6YnZBupXu9P2uziEaNWfZAa5InfBzidtf6jFSGqb8eTL7gXr9DJbNy072B7OYgmV6a39YPYCZZA==";
+
+    #[test]
+    fn signer_field() {
+        let mut permit = PermitBuilder::new()
+            .params(FillerPermit {
+                coins: vec![],
+                sender: SCOPED_ADDRESS.to_string(),
+                contract: "".to_string(),
+                execute_msg: EmptyMsg {},
+            })
+            .signature(PermitSignature::from_base64(SCOPED_PUBKEY, SENDER_SIGNED_TX).unwrap())
+            .build()
+            .unwrap();
+
+        let deps = mock_dependencies(20, &[]);
+        let msg_type = Some(FILLERPERMITNAME.to_string());
+
+        let addr = permit
+            .validate_signer_field(&deps.api, msg_type.clone(), |params| &params.sender)
+            .unwrap();
+        assert_eq!(
+            addr.as_canonical().unwrap(),
+            bech32_to_canonical(SCOPED_ADDRESS)
+        );
+
+        permit.params.sender = ADDRESS.to_string();
+        permit.signature.signature = Binary::from_base64(OTHER_SENDER_SIGNED_TX).unwrap();
+
+        // The signature is valid but the sender is someone else
+        assert!(permit.validate(&deps.api, msg_type.clone()).is_ok());
+        assert!(permit
+            .validate_signer_field(&deps.api, msg_type, |params| &params.sender)
+            .is_err());
+    }
+
+    #[test]
+    fn account_range() {
+        let permit = PermitBuilder::new()
+            .params(FillerPermit {
+                coins: vec![],
+                sender: "".to_string(),
+                contract: "".to_string(),
+                execute_msg: EmptyMsg {},
+            })
+            .chain_id("bombay-12")
+            .sequence(Uint128(0))
+            .signature(PermitSignature {
+                pub_key: PubKey::new(
+                    Binary::from_base64("A50CTeVnMYyZGh7K4x4NtdfG1H1oicog6lEoPMi65IK2").unwrap(),
+                ),
+                signature: Binary::from_base64(
+                    "75RcVHa/SW1WyjcFMkhZ63+D4ccxffchLvJPyURmtaskA8CPj+y6JSrpuRhxMC+1hdjSJC3c0IeJVbDIRapxPg==",
+                )
+                .unwrap(),
+            })
+            .account_number(Uint128(203289))
+            .memo("b64Encoded")
+            .build()
+            .unwrap();
+
+        let deps = mock_dependencies(20, &[]);
+        let msg_type = Some(FILLERPERMITNAME.to_string());
+
+        let addr = permit
+            .validate_with_account_range(&deps.api, msg_type.clone(), Uint128(1), Uint128(1000000))
+            .unwrap();
+        assert_eq!(
+            addr.as_canonical().unwrap(),
+            bech32_to_canonical("terra1m79yd3jh97vz4tqu0m8g49gfl7qmknhh23kac5")
+        );
+        assert!(permit
+            .validate_with_account_range(
+                &deps.api,
+                msg_type.clone(),
+                Uint128(203289),
+                Uint128(203289)
+            )
+            .is_ok());
+
+        match permit.validate_with_account_range(
+            &deps.api,
+            msg_type.clone(),
+            Uint128(203290),
+            Uint128(1000000),
+        ) {
+            Err(err) => {
+                assert_eq!(
+                    err,
+                    PermitError::AccountNumberOutOfRange {
+                        account_number: Uint128(203289),
+                        min: Uint128(203290),
+                        max: Uint128(1000000),
+                    }
+                );
+                assert_eq!(
+                    err.to_string(),
+                    "Account number 203289 is outside of 203290..=1000000"
+                );
+            }
+            Ok(_) => panic!("account number below min was accepted"),
+        }
+        assert!(permit
+            .validate_with_account_range(&deps.api, msg_type, Uint128(0), Uint128(203288))
+            .is_err());
+    }
+
     #[test]
     fn memo_signature() {
         let mut permit = MemoPermit {
@@ -170,7 +3232,11 @@ mod signature_tests {
                     "75RcVHa/SW1WyjcFMkhZ63+D4ccxffchLvJPyURmtaskA8CPj+y6JSrpuRhxMC+1hdjSJC3c0IeJVbDIRapxPg==").unwrap(),
             },
             account_number: Some(Uint128(203289)),
-            memo: Some("b64Encoded".to_string())
+            memo: Some("b64Encoded".to_string()),
+            expires: None,
+            code_hash: None,
+            action: None,
+            fee: None,
         };
 
         let deps = mock_dependencies(20, &[]);
@@ -179,11 +3245,11 @@ mod signature_tests {
             .validate(&deps.api, Some(FILLERPERMITNAME.to_string()))
             .unwrap();
         assert_eq!(
-            addr.as_canonical(),
+            addr.as_canonical().unwrap(),
             bech32_to_canonical("terra1m79yd3jh97vz4tqu0m8g49gfl7qmknhh23kac5")
         );
         assert_ne!(
-            addr.as_canonical(),
+            addr.as_canonical().unwrap(),
             bech32_to_canonical("secret102nasmxnxvwp5agc4lp3flc6s23335xm8g7gn9")
         );
 
@@ -192,4 +3258,107 @@ mod signature_tests {
         // NOTE: SN mock deps doesnt have a valid working implementation of the dep functons for some reason
         //assert!(permit.validate(&deps.api, Some(FILLERPERMITNAME.to_string())).is_err())
     }
+
+    fn memo_permit() -> MemoPermit {
+        MemoPermit {
+            params: FillerPermit {
+                coins: vec![],
+                sender: "".to_string(),
+                contract: "".to_string(),
+                execute_msg: EmptyMsg {},
+            },
+            chain_id: Some("bombay-12".to_string()),
+            sequence: Some(Uint128(0)),
+            signature: PermitSignature {
+                pub_key: PubKey::new(
+                    Binary::from_base64("A50CTeVnMYyZGh7K4x4NtdfG1H1oicog6lEoPMi65IK2").unwrap(),
+                ),
+                signature: Binary::from_base64(
+                    "75RcVHa/SW1WyjcFMkhZ63+D4ccxffchLvJPyURmtaskA8CPj+y6JSrpuRhxMC+1hdjSJC3c0IeJVbDIRapxPg==",
+                )
+                .unwrap(),
+            },
+            account_number: Some(Uint128(203289)),
+            memo: Some("b64Encoded".to_string()),
+            expires: None,
+            code_hash: None,
+            action: None,
+            fee: None,
+        }
+    }
+
+    #[test]
+    fn chain_id_prefix() {
+        let deps = mock_dependencies(20, &[]);
+
+        assert_eq!(
+            memo_permit()
+                .validate_as_humanaddr(&deps.api, Some(FILLERPERMITNAME.to_string()))
+                .unwrap(),
+            HumanAddr("terra1m79yd3jh97vz4tqu0m8g49gfl7qmknhh23kac5".to_string())
+        );
+    }
+
+    #[test]
+    fn validate_with_prefix() {
+        let permit = memo_permit();
+        let deps = mock_dependencies(20, &[]);
+
+        let addr = permit
+            .validate(&deps.api, Some(FILLERPERMITNAME.to_string()))
+            .unwrap();
+        assert_eq!(addr.prefix(), "secret");
+
+        let signer = permit
+            .validate_with_prefix(&deps.api, Some(FILLERPERMITNAME.to_string()), "terra")
+            .unwrap();
+        assert_eq!(signer, addr);
+        assert_eq!(signer.prefix(), "terra");
+        assert_eq!(
+            signer.as_humanaddr(None).unwrap(),
+            HumanAddr("terra1m79yd3jh97vz4tqu0m8g49gfl7qmknhh23kac5".to_string())
+        );
+        assert_eq!(
+            signer.as_humanaddr(Some("secret")).unwrap(),
+            addr.as_humanaddr(None).unwrap()
+        );
+
+        // The prefix is only checked once the address is encoded
+        let signer = permit
+            .validate_with_prefix(&deps.api, Some(FILLERPERMITNAME.to_string()), "")
+            .unwrap();
+        assert!(signer.as_humanaddr(None).is_err());
+    }
+
+    #[test]
+    fn memo_auth_context() {
+        let deps = mock_dependencies(20, &[]);
+
+        let context = memo_permit()
+            .validate_full(&deps.api, Some(FILLERPERMITNAME.to_string()), |params| {
+                &params.contract
+            })
+            .unwrap();
+        assert_eq!(
+            context,
+            AuthContext {
+                address: HumanAddr("terra1m79yd3jh97vz4tqu0m8g49gfl7qmknhh23kac5".to_string()),
+                canonical: bech32_to_canonical("terra1m79yd3jh97vz4tqu0m8g49gfl7qmknhh23kac5"),
+                chain_id: "bombay-12".to_string(),
+                permit_key: "".to_string(),
+                scheme: "tendermint/PubKeySecp256k1".to_string(),
+                sequence: Uint128(0),
+            }
+        );
+    }
+
+    #[test]
+    fn memo_signed_fields() {
+        let fields = memo_permit()
+            .signed_fields(Some(FILLERPERMITNAME.to_string()))
+            .unwrap();
+        assert!(fields.contains(&("msgs.0.value.coins".to_string(), "[]".to_string())));
+        assert!(fields.contains(&("msgs.0.value.execute_msg".to_string(), "{}".to_string())));
+        assert!(fields.contains(&("memo".to_string(), "b64Encoded".to_string())));
+    }
 }
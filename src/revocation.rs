@@ -0,0 +1,110 @@
+use crate::permit::Permit;
+use crate::transaction::PubKeyValue;
+use cosmwasm_std::{Api, CanonicalAddr, ReadonlyStorage, StdError, StdResult, Storage};
+use serde::Serialize;
+
+pub const REVOKED_PERMITS_KEY: &[u8] = b"revoked_permits";
+
+/// Permits are revoked per owner, so different users can reuse the same permit key
+fn revocation_key(owner: &CanonicalAddr, permit_key: &str) -> Vec<u8> {
+    let mut key = REVOKED_PERMITS_KEY.to_vec();
+    key.push(owner.len() as u8);
+    key.extend_from_slice(owner.as_slice());
+    key.extend_from_slice(permit_key.as_bytes());
+    key
+}
+
+pub fn revoke<S: Storage>(storage: &mut S, owner: &CanonicalAddr, permit_key: &str) {
+    storage.set(&revocation_key(owner, permit_key), &[1]);
+}
+
+pub fn is_revoked<S: ReadonlyStorage>(
+    storage: &S,
+    owner: &CanonicalAddr,
+    permit_key: &str,
+) -> bool {
+    storage.get(&revocation_key(owner, permit_key)).is_some()
+}
+
+impl<T: Serialize> Permit<T> {
+    /// Validates the permit and checks that its signer hasn't revoked it
+    pub fn validate_not_revoked<A: Api, S: ReadonlyStorage, F: Fn(&T) -> &str>(
+        &self,
+        api: &A,
+        storage: &S,
+        msg_type: Option<String>,
+        extract_key: F,
+    ) -> StdResult<PubKeyValue> {
+        let signer = self.validate(api, msg_type)?;
+        let permit_key = extract_key(&self.params);
+
+        if is_revoked(storage, &signer.as_signer_canonical()?, permit_key) {
+            return Err(StdError::generic_err(format!(
+                "Permit {} was revoked",
+                permit_key
+            )));
+        }
+
+        Ok(signer)
+    }
+}
+
+#[cfg(test)]
+mod revocation_tests {
+    use super::*;
+    use crate::permit::{bech32_to_canonical, PermitBuilder};
+    use crate::transaction::PermitSignature;
+    use cosmwasm_std::testing::{mock_dependencies, MockStorage};
+    use serde::Deserialize;
+
+    #[remain::sorted]
+    #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+    #[serde(rename_all = "snake_case")]
+    struct NamedPermitMsg {
+        pub permit_name: String,
+    }
+
+    const ADDRESS: &str = "secret1wxyx7cgz8pjcnzt0skqwaup8043sxnd7t7u8d8";
+    const PUBKEY: &str = "AimifPuikvLOiLaYvw+By4mBXcPsUVKr/7YWP1I6irWG";
+    const SIGNED_TX: &str =
+        "zp/4KtT7Zky3PCx0F7ck4cD2KT61z8ON+RQt2uKILmcq3PiYVL+MZ5/vjPtv1/6w1pG5OI4qIHUxy/5AxP4IKQ==";
+
+    #[test]
+    fn revoke_and_check() {
+        let mut storage = MockStorage::new();
+        let owner = bech32_to_canonical(ADDRESS);
+        let other = bech32_to_canonical("secret102nasmxnxvwp5agc4lp3flc6s23335xm8g7gn9");
+
+        assert!(!is_revoked(&storage, &owner, "balance"));
+
+        revoke(&mut storage, &owner, "balance");
+        assert!(is_revoked(&storage, &owner, "balance"));
+        assert!(!is_revoked(&storage, &owner, "history"));
+        assert!(!is_revoked(&storage, &other, "balance"));
+    }
+
+    #[test]
+    fn validate_revoked() {
+        let permit = PermitBuilder::new()
+            .params(NamedPermitMsg {
+                permit_name: "balance".to_string(),
+            })
+            .signature(PermitSignature::from_base64(PUBKEY, SIGNED_TX).unwrap())
+            .build()
+            .unwrap();
+
+        let mut deps = mock_dependencies(20, &[]);
+
+        let addr = permit
+            .validate_not_revoked(&deps.api, &deps.storage, None, |params| &params.permit_name)
+            .unwrap();
+        assert_eq!(addr.as_canonical().unwrap(), bech32_to_canonical(ADDRESS));
+
+        revoke(&mut deps.storage, &bech32_to_canonical(ADDRESS), "balance");
+        assert!(permit
+            .validate_not_revoked(&deps.api, &deps.storage, None, |params| {
+                &params.permit_name
+            })
+            .is_err());
+    }
+}
@@ -0,0 +1,480 @@
+use crate::compact::{canonical_number, Error};
+use serde::{ser, Serialize};
+
+/// Upper bound of the length of the JSON compact::to_vec produces for this value,
+/// computed without building the output. Values that fail to serialize return usize::MAX.
+pub fn estimate_serialized_size<T: Serialize + ?Sized>(value: &T) -> usize {
+    let mut counter = SizeCounter { size: 0 };
+    match value.serialize(&mut counter) {
+        Ok(()) => counter.size,
+        Err(_) => usize::MAX,
+    }
+}
+
+/// Quoted string where every escaped char is assumed to take the longest \u00XX form
+fn str_size(v: &str) -> usize {
+    2 + v
+        .bytes()
+        .map(|b| match b {
+            b'"' | b'\\' => 2,
+            0x00..=0x1f => 6,
+            _ => 1,
+        })
+        .sum::<usize>()
+}
+
+fn digits(mut v: u128) -> usize {
+    let mut len = 1;
+    while v >= 10 {
+        v /= 10;
+        len += 1;
+    }
+    len
+}
+
+fn signed_digits(v: i128) -> usize {
+    if v < 0 {
+        1 + digits(v.unsigned_abs())
+    } else {
+        digits(v as u128)
+    }
+}
+
+struct SizeCounter {
+    size: usize,
+}
+
+struct Compound<'a> {
+    counter: &'a mut SizeCounter,
+    first: bool,
+    /// Closing brackets still to be written
+    closing: usize,
+}
+
+impl<'a> Compound<'a> {
+    fn new(counter: &'a mut SizeCounter, closing: usize) -> Self {
+        // Opening brackets mirror the closing ones
+        counter.size += closing;
+        Self {
+            counter,
+            first: true,
+            closing,
+        }
+    }
+
+    fn variant(counter: &'a mut SizeCounter, variant: &str) -> Self {
+        // {"variant":[ or {"variant":{
+        counter.size += str_size(variant) + 1;
+        Self::new(counter, 2)
+    }
+
+    fn separator(&mut self) {
+        if !self.first {
+            self.counter.size += 1;
+        }
+        self.first = false;
+    }
+
+    fn element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.separator();
+        value.serialize(&mut *self.counter)
+    }
+
+    fn field<T: Serialize + ?Sized>(&mut self, key: &str, value: &T) -> Result<(), Error> {
+        self.separator();
+        self.counter.size += str_size(key) + 1;
+        value.serialize(&mut *self.counter)
+    }
+
+    fn finish(self) -> Result<(), Error> {
+        self.counter.size += self.closing;
+        Ok(())
+    }
+}
+
+impl<'a> ser::Serializer for &'a mut SizeCounter {
+    type Ok = ();
+    type Error = Error;
+    type SerializeSeq = Compound<'a>;
+    type SerializeTuple = Compound<'a>;
+    type SerializeTupleStruct = Compound<'a>;
+    type SerializeTupleVariant = Compound<'a>;
+    type SerializeMap = Compound<'a>;
+    type SerializeStruct = Compound<'a>;
+    type SerializeStructVariant = Compound<'a>;
+
+    fn serialize_bool(self, _v: bool) -> Result<(), Error> {
+        self.size += 5;
+        Ok(())
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<(), Error> {
+        self.serialize_i128(v as i128)
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<(), Error> {
+        self.serialize_i128(v as i128)
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<(), Error> {
+        self.serialize_i128(v as i128)
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<(), Error> {
+        self.serialize_i128(v as i128)
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<(), Error> {
+        self.size += signed_digits(v);
+        Ok(())
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<(), Error> {
+        self.serialize_u128(v as u128)
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<(), Error> {
+        self.serialize_u128(v as u128)
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<(), Error> {
+        self.serialize_u128(v as u128)
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<(), Error> {
+        self.serialize_u128(v as u128)
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<(), Error> {
+        self.size += digits(v);
+        Ok(())
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<(), Error> {
+        if !v.is_finite() {
+            return Err(ser::Error::custom("Not a JSON number"));
+        }
+        self.size += canonical_number(v).len();
+        Ok(())
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<(), Error> {
+        if !v.is_finite() {
+            return Err(ser::Error::custom("Not a JSON number"));
+        }
+        self.size += canonical_number(v).len();
+        Ok(())
+    }
+
+    fn serialize_char(self, v: char) -> Result<(), Error> {
+        self.size += str_size(v.encode_utf8(&mut [0; 4]));
+        Ok(())
+    }
+
+    fn serialize_str(self, v: &str) -> Result<(), Error> {
+        self.size += str_size(v);
+        Ok(())
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<(), Error> {
+        // Quoted padded base64, 4 chars for every 3 bytes
+        self.size += 2 + 4 * v.len().div_ceil(3);
+        Ok(())
+    }
+
+    fn serialize_none(self) -> Result<(), Error> {
+        self.serialize_unit()
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<(), Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<(), Error> {
+        self.size += 4;
+        Ok(())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<(), Error> {
+        self.serialize_unit()
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<(), Error> {
+        self.size += str_size(variant);
+        Ok(())
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        // {"variant":value}
+        self.size += 2 + str_size(variant) + 1;
+        value.serialize(self)
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Compound<'a>, Error> {
+        Ok(Compound::new(self, 1))
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Compound<'a>, Error> {
+        Ok(Compound::new(self, 1))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Compound<'a>, Error> {
+        Ok(Compound::new(self, 1))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Compound<'a>, Error> {
+        Ok(Compound::variant(self, variant))
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Compound<'a>, Error> {
+        Ok(Compound::new(self, 1))
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Compound<'a>, Error> {
+        Ok(Compound::new(self, 1))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Compound<'a>, Error> {
+        Ok(Compound::variant(self, variant))
+    }
+}
+
+impl<'a> ser::SerializeSeq for Compound<'a> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.finish()
+    }
+}
+
+impl<'a> ser::SerializeTuple for Compound<'a> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.finish()
+    }
+}
+
+impl<'a> ser::SerializeTupleStruct for Compound<'a> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.finish()
+    }
+}
+
+impl<'a> ser::SerializeTupleVariant for Compound<'a> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.finish()
+    }
+}
+
+impl<'a> ser::SerializeMap for Compound<'a> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), Error> {
+        // Non string keys get quoted, so count them as such
+        self.separator();
+        self.counter.size += 2 + 1;
+        key.serialize(&mut *self.counter)
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        value.serialize(&mut *self.counter)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.finish()
+    }
+}
+
+impl<'a> ser::SerializeStruct for Compound<'a> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        self.field(key, value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.finish()
+    }
+}
+
+impl<'a> ser::SerializeStructVariant for Compound<'a> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        self.field(key, value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.finish()
+    }
+}
+
+#[cfg(test)]
+mod size_tests {
+    use super::*;
+    use crate::compact::to_vec;
+    use crate::permit::PermitBuilder;
+    use crate::transaction::{PermitSignature, PubKey, SignedTx};
+    use cosmwasm_std::{Binary, Uint128};
+    use serde::{Deserialize, Serialize};
+
+    #[remain::sorted]
+    #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+    #[serde(rename_all = "snake_case")]
+    struct TestPermitMsg {
+        pub address: String,
+        pub some_number: Uint128,
+    }
+
+    #[derive(Serialize)]
+    #[serde(rename_all = "snake_case")]
+    enum Variants {
+        Unit,
+        Newtype(i64),
+        Escaped(String),
+    }
+
+    /// Serializes through serialize_bytes like serde_bytes does
+    struct RawBytes(Vec<u8>);
+
+    impl Serialize for RawBytes {
+        fn serialize<S: ser::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.serialize_bytes(&self.0)
+        }
+    }
+
+    fn assert_upper_bound<T: Serialize>(value: &T) {
+        let actual = to_vec(value).unwrap().len();
+        let estimate = estimate_serialized_size(value);
+        assert!(
+            estimate >= actual,
+            "estimated {} but serialized {}",
+            estimate,
+            actual
+        );
+    }
+
+    #[test]
+    fn estimates_cover_output() {
+        let mut permit = PermitBuilder::new()
+            .params(TestPermitMsg {
+                address: "secret102nasmxnxvwp5agc4lp3flc6s23335xm8g7gn9".to_string(),
+                some_number: Uint128(10),
+            })
+            .chain_id("pulsar-1")
+            .signature(PermitSignature {
+                pub_key: PubKey::new(
+                    Binary::from_base64("A0qzJ3s16OKUfn1KFyh533vBnBOQIT0jm+R/FBobJCfa").unwrap(),
+                ),
+                signature: Binary::from_base64("4pZtghyHKHHmwiGNC5JD8JxCJiO+44j6GqaLPc19Q7lt85tr0IRZHYcnc0pkokIds8otxU9rcuvPXb0+etLyVA==").unwrap(),
+            })
+            .memo("\"quoted\"\n\\")
+            .build()
+            .unwrap();
+        assert_upper_bound(&permit);
+        assert_upper_bound(&permit.create_signed_tx(None));
+
+        permit.memo = None;
+        let signed_tx: SignedTx<TestPermitMsg> = permit.create_signed_tx(None);
+        // Plain strings and numbers are counted exactly
+        assert_eq!(
+            estimate_serialized_size(&signed_tx),
+            to_vec(&signed_tx).unwrap().len()
+        );
+
+        assert_upper_bound(&vec![
+            Variants::Unit,
+            Variants::Newtype(-1234),
+            Variants::Escaped("\u{1}tab\t".to_string()),
+        ]);
+
+        assert_upper_bound(&vec![Some(1u64), None]);
+        for len in 0..5 {
+            let bytes = RawBytes(vec![0xff; len]);
+            assert_eq!(
+                estimate_serialized_size(&bytes),
+                to_vec(&bytes).unwrap().len()
+            );
+        }
+        assert_upper_bound(&(u64::MAX, i64::MIN, true, ()));
+
+        // Floats are counted exactly
+        let floats = (0.1f64, -1e100f64, f64::MIN_POSITIVE, 0.5f32);
+        assert_eq!(
+            estimate_serialized_size(&floats),
+            to_vec(&floats).unwrap().len()
+        );
+        assert_eq!(estimate_serialized_size(&f64::NAN), usize::MAX);
+    }
+}
@@ -1,10 +1,15 @@
-use crate::permit::Permit;
-use crate::sha_256;
+use crate::compact;
+use crate::permit::{Permit, PermitError};
+use crate::{sha_256, SHA256_HASH_SIZE};
 use bech32::{ToBase32, Variant};
-use cosmwasm_std::{Binary, CanonicalAddr, HumanAddr, StdError, StdResult, Uint128};
+use cosmwasm_std::{Api, Binary, CanonicalAddr, HumanAddr, StdError, StdResult, Uint128};
 use ripemd160::{Digest, Ripemd160};
 use schemars::JsonSchema;
+use secp256k1::PublicKey;
 use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use sha3::Keccak256;
+use std::io;
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
@@ -13,15 +18,71 @@ pub struct PermitSignature {
     pub signature: Binary,
 }
 
+impl PermitSignature {
+    /// Decodes the base64 pubkey and signature a wallet returns, the key is a secp256k1 one
+    pub fn from_base64(pubkey_b64: &str, sig_b64: &str) -> StdResult<Self> {
+        Ok(Self {
+            pub_key: PubKey::new(Binary::from_base64(pubkey_b64)?),
+            signature: Binary::from_base64(sig_b64)?,
+        })
+    }
+
+    /// Builds a signature from a DER encoded secp256k1 signature, high-S signatures are
+    /// normalized into the compact R || S form secp256k1_verify expects
+    pub fn from_der(pub_key: PubKey, der: &[u8]) -> StdResult<Self> {
+        let mut signature = secp256k1::Signature::from_der(der)
+            .map_err(|err| StdError::generic_err(format!("Invalid DER signature: {}", err)))?;
+        signature.normalize_s();
+
+        Ok(Self {
+            pub_key,
+            signature: Binary(signature.serialize_compact().to_vec()),
+        })
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub struct PubKey {
-    /// ignored, but must be "tendermint/PubKeySecp256k1" otherwise the verification will fail
+    /// Must be the pubkey type of one of the SigScheme variants
     pub r#type: String,
     /// Secp256k1 PubKey
     pub value: Binary,
 }
 
+/// Key type used by ethermint based chains (i.e. Injective), these sign the amino sign-doc
+/// with Keccak256 and derive their addresses the Ethereum way
+pub const ETH_SECP256K1_TYPE: &str = "ethermint/PubKeyEthSecp256k1";
+
+/// Signature schemes a permit can be signed with
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SigScheme {
+    Secp256k1,
+    EthSecp256k1,
+    Ed25519,
+}
+
+impl SigScheme {
+    pub const ALL: [SigScheme; 3] = [
+        SigScheme::Secp256k1,
+        SigScheme::EthSecp256k1,
+        SigScheme::Ed25519,
+    ];
+
+    /// Amino type of the pubkeys using this scheme
+    pub fn pubkey_type(&self) -> &'static str {
+        match self {
+            SigScheme::Secp256k1 => "tendermint/PubKeySecp256k1",
+            SigScheme::EthSecp256k1 => ETH_SECP256K1_TYPE,
+            SigScheme::Ed25519 => ED25519_TYPE,
+        }
+    }
+}
+
+/// Ed25519 keys sign the SHA-256 hash of the sign-doc
+pub const ED25519_TYPE: &str = "tendermint/PubKeyEd25519";
+
 impl PubKey {
     pub fn new(pubkey: Binary) -> Self {
         Self {
@@ -29,29 +90,299 @@ impl PubKey {
             value: pubkey,
         }
     }
+
+    /// Scheme of this key, unknown key types list the supported ones so clients can retry
+    pub fn scheme(&self) -> Result<SigScheme, PermitError> {
+        SigScheme::ALL
+            .iter()
+            .find(|scheme| scheme.pubkey_type() == self.r#type)
+            .copied()
+            .ok_or_else(|| PermitError::UnsupportedScheme(self.r#type.clone()))
+    }
+
+    pub fn is_eth_secp256k1(&self) -> bool {
+        self.r#type == ETH_SECP256K1_TYPE
+    }
+
+    /// Hashes the sign-doc the way this key type signs it. Ethereum signers left out to be
+    /// recovered signed with personal_sign, which hashes the EIP-191 wrapped sign-doc
+    pub fn hash_sign_doc(&self, signed_bytes: &[u8]) -> [u8; SHA256_HASH_SIZE] {
+        if !self.is_eth_secp256k1() {
+            return sha_256(signed_bytes);
+        }
+
+        if self.value.is_empty() {
+            return keccak_256(&compact::eth_signed_message(signed_bytes));
+        }
+
+        keccak_256(signed_bytes)
+    }
+
+    /// Same hash as hash_sign_doc, computed while the sign-doc is serialized so only the
+    /// digest is held. Recovered Ethereum signers serialize it twice, the EIP-191 prefix
+    /// holds its length
+    pub fn hash_serialized_sign_doc<S: Serialize + ?Sized>(
+        &self,
+        sign_doc: &S,
+    ) -> StdResult<[u8; SHA256_HASH_SIZE]> {
+        if !self.is_eth_secp256k1() {
+            return hash_serialized::<Sha256, _>(&[], sign_doc);
+        }
+
+        if self.value.is_empty() {
+            let mut len = ByteCount(0);
+            compact::to_writer(&mut len, sign_doc)?;
+            let prefix = compact::eth_signed_message_prefix(len.0);
+            return hash_serialized::<Keccak256, _>(&prefix, sign_doc);
+        }
+
+        hash_serialized::<Keccak256, _>(&[], sign_doc)
+    }
+}
+
+fn keccak_256(data: &[u8]) -> [u8; SHA256_HASH_SIZE] {
+    let mut result = [0u8; SHA256_HASH_SIZE];
+    result.copy_from_slice(&Keccak256::digest(data));
+    result
 }
 
-pub struct PubKeyValue(pub Binary);
+/// Hashes everything written into it
+struct HashWriter<D>(D);
 
-impl PubKeyValue {
-    pub fn as_canonical(&self) -> CanonicalAddr {
+impl<D: Digest> io::Write for HashWriter<D> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Counts the bytes written into it
+struct ByteCount(usize);
+
+impl io::Write for ByteCount {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0 += buf.len();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn hash_serialized<D: Digest, S: Serialize + ?Sized>(
+    prefix: &[u8],
+    value: &S,
+) -> StdResult<[u8; SHA256_HASH_SIZE]> {
+    let mut hasher = HashWriter(D::new());
+    hasher.0.update(prefix);
+    compact::to_writer(&mut hasher, value)?;
+    let mut result = [0u8; SHA256_HASH_SIZE];
+    result.copy_from_slice(&hasher.0.finalize());
+    Ok(result)
+}
+
+/// Signer pubkey, remembers the bech32 prefix it was validated for so the address is only
+/// encoded once asked for, and the scheme it signed with
+#[derive(Clone, Debug)]
+pub struct PubKeyValue(pub Binary, Option<String>, SigScheme);
+
+/// Same key, whatever prefix it was validated for
+impl PartialEq for PubKeyValue {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl From<Binary> for PubKeyValue {
+    fn from(pubkey: Binary) -> Self {
+        Self::new(pubkey)
+    }
+}
+
+/// Derives the address bytes of a secp256k1 pubkey, lets chains with other address hashes
+/// reuse PubKeyValue
+pub trait AddressScheme {
+    fn canonical(pubkey: &[u8]) -> StdResult<CanonicalAddr>;
+}
+
+/// RIPEMD160(SHA256(pubkey)), used by Cosmos SDK chains
+pub struct CosmosAddressScheme;
+
+impl AddressScheme for CosmosAddressScheme {
+    fn canonical(pubkey: &[u8]) -> StdResult<CanonicalAddr> {
         let mut hasher = Ripemd160::new();
-        hasher.update(sha_256(&self.0 .0));
-        CanonicalAddr(Binary(hasher.finalize().to_vec()))
+        hasher.update(sha_256(pubkey));
+        Ok(CanonicalAddr(Binary(hasher.finalize().to_vec())))
+    }
+}
+
+/// SHA256(pubkey) truncated to 20 bytes, used by tendermint ed25519 keys
+pub struct Ed25519AddressScheme;
+
+impl AddressScheme for Ed25519AddressScheme {
+    fn canonical(pubkey: &[u8]) -> StdResult<CanonicalAddr> {
+        Ok(CanonicalAddr(Binary(sha_256(pubkey)[..20].to_vec())))
+    }
+}
+
+/// BLAKE2b-256(pubkey) truncated to 20 bytes
+pub struct Blake2bAddressScheme;
+
+impl AddressScheme for Blake2bAddressScheme {
+    fn canonical(pubkey: &[u8]) -> StdResult<CanonicalAddr> {
+        use blake2::digest::{Update, VariableOutput};
+
+        let mut hasher =
+            blake2::VarBlake2b::new(32).map_err(|err| StdError::generic_err(err.to_string()))?;
+        hasher.update(pubkey);
+
+        let mut addr = vec![];
+        hasher.finalize_variable(|hash| addr.extend_from_slice(&hash[..20]));
+        Ok(CanonicalAddr(Binary(addr)))
+    }
+}
+
+impl PubKeyValue {
+    pub fn new(pubkey: Binary) -> Self {
+        PubKeyValue(pubkey, None, SigScheme::Secp256k1)
+    }
+
+    /// Same key, its address is derived the way scheme requires
+    pub fn with_scheme(mut self, scheme: SigScheme) -> Self {
+        self.2 = scheme;
+        self
+    }
+
+    /// Scheme the key signed with, secp256k1 unless validated for another
+    pub fn scheme(&self) -> SigScheme {
+        self.2
+    }
+
+    /// Same key, as_humanaddr(None) encodes its address with prefix
+    pub fn with_prefix(mut self, prefix: &str) -> Self {
+        self.1 = Some(prefix.to_string());
+        self
+    }
+
+    /// Bech32 prefix used when as_humanaddr gets None, secret unless validated for another
+    pub fn prefix(&self) -> &str {
+        self.1.as_deref().unwrap_or("secret")
+    }
+
+    /// Raw key bytes, the same ones the permit was signed with
+    pub fn as_binary(&self) -> &Binary {
+        &self.0
+    }
+
+    /// Base64 key, handy for events and logs
+    pub fn to_base64(&self) -> String {
+        self.0.to_base64()
+    }
+
+    /// Address bytes of the signer, same as as_signer_canonical
+    pub fn as_canonical(&self) -> StdResult<CanonicalAddr> {
+        self.as_signer_canonical()
+    }
+
+    /// Address bytes derived with the given scheme
+    pub fn as_scheme_canonical<S: AddressScheme>(&self) -> StdResult<CanonicalAddr> {
+        S::canonical(self.0.as_slice())
+    }
+
+    /// Address bytes derived the way the key's scheme requires
+    pub fn as_signer_canonical(&self) -> StdResult<CanonicalAddr> {
+        match self.2 {
+            SigScheme::Secp256k1 => self.as_scheme_canonical::<CosmosAddressScheme>(),
+            SigScheme::EthSecp256k1 => self.as_eth_canonical(),
+            SigScheme::Ed25519 => self.as_scheme_canonical::<Ed25519AddressScheme>(),
+        }
+    }
+
+    /// Compares the derived address bytes directly, no bech32 prefix involved
+    pub fn matches(&self, expected: &CanonicalAddr) -> bool {
+        self.as_signer_canonical()
+            .is_ok_and(|canonical| canonical == *expected)
+    }
+
+    /// Checks a second signature over the same sign-doc hash from a registered device key,
+    /// the device key has to differ from this one
+    pub fn verify_cosign<A: Api>(
+        &self,
+        api: &A,
+        sign_doc_hash: &[u8],
+        cosign: &Binary,
+        device_pubkey: &Binary,
+    ) -> StdResult<bool> {
+        if *device_pubkey == self.0 {
+            return Err(StdError::generic_err(
+                "Co-signature must come from a different key",
+            ));
+        }
+
+        api.secp256k1_verify(sign_doc_hash, cosign.as_slice(), device_pubkey.as_slice())
+            .map_err(|err| StdError::generic_err(err.to_string()))
     }
 
     pub fn as_humanaddr(&self, perfix: Option<&str>) -> StdResult<HumanAddr> {
         let pre = match perfix {
-            None => "secret",
+            None => self.prefix(),
             Some(p) => p,
         };
 
-        let acc = self.as_canonical().as_slice().to_base32();
+        let acc = self.as_canonical()?.as_slice().to_base32();
         Ok(HumanAddr(
             bech32::encode(pre, acc, Variant::Bech32)
                 .map_err(|err| StdError::generic_err(err.to_string()))?,
         ))
     }
+
+    /// Address bytes derived the Ethereum way
+    pub fn as_eth_canonical(&self) -> StdResult<CanonicalAddr> {
+        let pubkey = PublicKey::from_slice(&self.0 .0)
+            .map_err(|err| StdError::generic_err(err.to_string()))?;
+
+        // Ethereum hashes the uncompressed key without its 0x04 tag
+        let hash = Keccak256::digest(&pubkey.serialize_uncompressed()[1..]);
+        Ok(CanonicalAddr(Binary(hash[12..].to_vec())))
+    }
+
+    /// Lowercase, 0x prefixed Ethereum address derived from the same secp256k1 key
+    pub fn as_eth_address(&self) -> StdResult<String> {
+        let mut addr = "0x".to_string();
+        for byte in self.as_eth_canonical()?.as_slice() {
+            addr.push_str(&format!("{:02x}", byte));
+        }
+        Ok(addr)
+    }
+
+    /// Ethereum address in its EIP-55 mixed case checksum form
+    pub fn as_eth_address_checksummed(&self) -> StdResult<String> {
+        let addr = self.as_eth_address()?;
+        let hex = &addr[2..];
+        let hash = Keccak256::digest(hex.as_bytes());
+
+        let mut checksummed = "0x".to_string();
+        for (i, c) in hex.chars().enumerate() {
+            // Letters are uppercased when the matching hash nibble is 8 or higher
+            let nibble = (hash[i / 2] >> (4 * (1 - i % 2))) & 0x0f;
+            if nibble >= 8 {
+                checksummed.push(c.to_ascii_uppercase());
+            } else {
+                checksummed.push(c);
+            }
+        }
+        Ok(checksummed)
+    }
+
+    /// Returns both the bech32 and Ethereum addresses owned by this key
+    pub fn linked_addresses(&self, prefix: Option<&str>) -> StdResult<(HumanAddr, String)> {
+        Ok((self.as_humanaddr(prefix)?, self.as_eth_address()?))
+    }
 }
 
 #[remain::sorted]
@@ -77,8 +408,17 @@ impl<T: Clone + Serialize> TxMsg<T> {
 pub struct SignedTx<T> {
     /// ignored
     pub account_number: Uint128,
+    /// Left out when None so permits without an action keep their signatures
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub action: Option<String>,
     /// ignored, no Env in query
     pub chain_id: String,
+    /// Left out when None so unbound permits keep their signatures
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code_hash: Option<String>,
+    /// Left out when None so permits without expiration keep their signatures
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires: Option<u64>,
     /// ignored
     pub fee: Fee,
     /// ignored
@@ -92,16 +432,193 @@ pub struct SignedTx<T> {
 impl<T: Clone + Serialize> SignedTx<T> {
     pub fn from_permit(permit: &Permit<T>, msg_type: Option<String>) -> Self {
         Self {
-            account_number: permit.account_number.unwrap_or(Uint128::zero()),
-            chain_id: permit.chain_id.clone().unwrap_or("secret-4".to_string()),
-            fee: Default::default(),
+            account_number: permit.effective_account_number(),
+            action: permit.action.clone(),
+            chain_id: permit.effective_chain_id().to_string(),
+            code_hash: permit.code_hash.clone(),
+            expires: permit.expires,
+            fee: permit.fee.clone().unwrap_or_default(),
             memo: permit.memo.clone().unwrap_or_default(),
             msgs: vec![TxMsg::new(permit.params.clone(), msg_type)],
-            sequence: permit.sequence.unwrap_or(Uint128::zero()),
+            sequence: permit.effective_sequence(),
+        }
+    }
+
+    /// Sha256 of the sign-doc, the hash cosmos and ed25519 keys sign
+    pub fn sha256_hash(&self) -> StdResult<[u8; SHA256_HASH_SIZE]> {
+        Ok(sha_256(&compact::to_vec(self)?))
+    }
+
+    /// Keccak256 of the sign-doc, the hash ethermint keys sign
+    pub fn eth_hash(&self) -> StdResult<[u8; SHA256_HASH_SIZE]> {
+        Ok(keccak_256(&compact::to_vec(self)?))
+    }
+
+    /// Sign-doc with one message per params entry, in order
+    pub fn from_permit_multi(permit: &Permit<Vec<T>>, msg_type: Option<String>) -> Self {
+        Self {
+            account_number: permit.effective_account_number(),
+            action: permit.action.clone(),
+            chain_id: permit.effective_chain_id().to_string(),
+            code_hash: permit.code_hash.clone(),
+            expires: permit.expires,
+            fee: permit.fee.clone().unwrap_or_default(),
+            memo: permit.memo.clone().unwrap_or_default(),
+            msgs: permit
+                .params
+                .iter()
+                .map(|params| TxMsg::new(params.clone(), msg_type.clone()))
+                .collect(),
+            sequence: permit.effective_sequence(),
+        }
+    }
+}
+
+/// Borrowed version of TxMsg, serializes into the exact same bytes
+#[remain::sorted]
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub struct TxMsgRef<'a, T> {
+    pub r#type: String,
+    pub value: &'a T,
+}
+
+/// Borrowed version of SignedTx, used for validation so params don't need to be cloned
+#[remain::sorted]
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub struct SignedTxRef<'a, T> {
+    pub account_number: Uint128,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub action: Option<&'a str>,
+    pub chain_id: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code_hash: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expires: Option<u64>,
+    pub fee: Fee,
+    /// None is left out of the sign-doc, some wallets omit an empty memo
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memo: Option<&'a str>,
+    pub msgs: Vec<TxMsgRef<'a, T>>,
+    pub sequence: Uint128,
+}
+
+impl<'a, T: Serialize> SignedTxRef<'a, T> {
+    pub fn from_permit(permit: &'a Permit<T>, msg_type: Option<String>) -> Self {
+        Self {
+            account_number: permit.effective_account_number(),
+            action: permit.action.as_deref(),
+            chain_id: permit.effective_chain_id(),
+            code_hash: permit.code_hash.as_deref(),
+            expires: permit.expires,
+            fee: permit.fee.clone().unwrap_or_default(),
+            memo: Some(permit.memo.as_deref().unwrap_or_default()),
+            msgs: vec![TxMsgRef {
+                r#type: msg_type.unwrap_or("signature_proof".to_string()),
+                value: &permit.params,
+            }],
+            sequence: permit.effective_sequence(),
+        }
+    }
+
+    /// Borrowed version of SignedTx::from_permit_multi
+    pub fn from_permit_multi(permit: &'a Permit<Vec<T>>, msg_type: Option<String>) -> Self {
+        let msg_type = msg_type.unwrap_or("signature_proof".to_string());
+        Self {
+            account_number: permit.effective_account_number(),
+            action: permit.action.as_deref(),
+            chain_id: permit.effective_chain_id(),
+            code_hash: permit.code_hash.as_deref(),
+            expires: permit.expires,
+            fee: permit.fee.clone().unwrap_or_default(),
+            memo: Some(permit.memo.as_deref().unwrap_or_default()),
+            msgs: permit
+                .params
+                .iter()
+                .map(|value| TxMsgRef {
+                    r#type: msg_type.clone(),
+                    value,
+                })
+                .collect(),
+            sequence: permit.effective_sequence(),
         }
     }
 }
 
+/// The one definition of what gets signed for a permit, clients build it with new
+/// and contracts validate against from_permit
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(transparent)]
+pub struct SigningPayload<'a, T>(SignedTxRef<'a, T>);
+
+impl<'a, T: Serialize> SigningPayload<'a, T> {
+    pub fn new(
+        params: &'a T,
+        chain_id: &'a str,
+        account_number: Uint128,
+        sequence: Uint128,
+        memo: &'a str,
+        msg_type: Option<String>,
+    ) -> Self {
+        Self(SignedTxRef {
+            account_number,
+            action: None,
+            chain_id,
+            code_hash: None,
+            expires: None,
+            fee: Default::default(),
+            memo: Some(memo),
+            msgs: vec![TxMsgRef {
+                r#type: msg_type.unwrap_or("signature_proof".to_string()),
+                value: params,
+            }],
+            sequence,
+        })
+    }
+
+    pub fn from_permit(permit: &'a Permit<T>, msg_type: Option<String>) -> Self {
+        Self(SignedTxRef::from_permit(permit, msg_type))
+    }
+
+    /// Commits to the single action the permit is meant for
+    pub fn with_action(mut self, action: Option<&'a str>) -> Self {
+        self.0.action = action;
+        self
+    }
+
+    /// Binds the permit to a contract code hash
+    pub fn with_code_hash(mut self, code_hash: Option<&'a str>) -> Self {
+        self.0.code_hash = code_hash;
+        self
+    }
+
+    /// Signs an expiration, leave it unset for permits that never expire
+    pub fn with_expires(mut self, expires: Option<u64>) -> Self {
+        self.0.expires = expires;
+        self
+    }
+
+    /// Signs another fee than the default one
+    pub fn with_fee(mut self, fee: Fee) -> Self {
+        self.0.fee = fee;
+        self
+    }
+
+    /// Leaves an empty memo out of the sign-doc instead of signing "memo":""
+    pub fn omit_empty_memo(mut self) -> Self {
+        if self.0.memo == Some("") {
+            self.0.memo = None;
+        }
+        self
+    }
+
+    /// Bytes to sign, hash them with the signing key's hash before signing
+    pub fn to_bytes(&self) -> StdResult<Vec<u8>> {
+        compact::to_vec(self)
+    }
+}
+
 #[remain::sorted]
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
@@ -110,6 +627,12 @@ pub struct Fee {
     pub gas: Uint128,
 }
 
+impl Fee {
+    pub fn new(amount: Vec<Coin>, gas: Uint128) -> Self {
+        Self { amount, gas }
+    }
+}
+
 impl Default for Fee {
     fn default() -> Self {
         Self {
@@ -127,6 +650,15 @@ pub struct Coin {
     pub denom: String,
 }
 
+impl Coin {
+    pub fn new(denom: &str, amount: Uint128) -> Self {
+        Self {
+            amount,
+            denom: denom.to_string(),
+        }
+    }
+}
+
 impl Default for Coin {
     fn default() -> Self {
         Self {
@@ -135,3 +667,84 @@ impl Default for Coin {
         }
     }
 }
+
+#[cfg(test)]
+mod pubkey_tests {
+    use super::*;
+    use crate::permit::bech32_to_canonical;
+
+    // Compressed secp256k1 generator point, the public key of private key 1
+    const PUBKEY: &str = "Anm+Zn753LusVaBilc6HCwcCm/zbLc4o2VnygVsW+BeY";
+
+    // Signer of the ethereum signature permit fixture
+    const ETH_PUBKEY: &str = "AimifPuikvLOiLaYvw+By4mBXcPsUVKr/7YWP1I6irWG";
+
+    // Signer of the ed25519 permit fixture
+    const ED25519_PUBKEY: &str = "nTtJB6dHKhY19lHq6IUk09APbk8xPI6PZTBPd0IUqdQ=";
+
+    #[test]
+    fn linked_addresses() {
+        let pubkey = PubKeyValue::new(Binary::from_base64(ETH_PUBKEY).unwrap());
+        let (human, eth) = pubkey.linked_addresses(None).unwrap();
+
+        assert_eq!(
+            human,
+            HumanAddr("secret1wxyx7cgz8pjcnzt0skqwaup8043sxnd7t7u8d8".to_string())
+        );
+        assert_eq!(eth, "0xbf3826b915adedf4c69d4914f1cd67a537032560");
+    }
+
+    #[test]
+    fn address_schemes() {
+        let pubkey = PubKeyValue::new(Binary::from_base64(PUBKEY).unwrap());
+
+        assert_eq!(
+            pubkey.as_scheme_canonical::<CosmosAddressScheme>().unwrap(),
+            pubkey.as_canonical().unwrap()
+        );
+        assert_eq!(
+            pubkey
+                .as_scheme_canonical::<Blake2bAddressScheme>()
+                .unwrap(),
+            bech32_to_canonical("secret1996lr55tj2mws3yehqas09l02g6420htvcqyt3")
+        );
+    }
+
+    #[test]
+    fn matches_canonical() {
+        let pubkey = PubKeyValue::new(Binary::from_base64(PUBKEY).unwrap());
+
+        assert!(pubkey.matches(&bech32_to_canonical(
+            "secret1w508d6qejxtdg4y5r3zarvary0c5xw7kccrnjy"
+        )));
+        assert!(!pubkey.matches(&bech32_to_canonical(
+            "secret1996lr55tj2mws3yehqas09l02g6420htvcqyt3"
+        )));
+
+        // Ethermint keys own the Ethereum address of the same key
+        let eth = pubkey.clone().with_scheme(SigScheme::EthSecp256k1);
+        assert!(eth.matches(&pubkey.as_eth_canonical().unwrap()));
+        assert!(!eth.matches(&pubkey.as_canonical().unwrap()));
+
+        let ed25519 = PubKeyValue::new(Binary::from_base64(ED25519_PUBKEY).unwrap())
+            .with_scheme(SigScheme::Ed25519);
+        assert!(ed25519.matches(&bech32_to_canonical(
+            "secret18lmw4deaemr9dc4m7g490ujazmmwegu78lyrw6"
+        )));
+        assert!(!ed25519.matches(
+            &ed25519
+                .as_scheme_canonical::<CosmosAddressScheme>()
+                .unwrap()
+        ));
+    }
+
+    #[test]
+    fn checksummed_eth_address() {
+        let pubkey = PubKeyValue::new(Binary::from_base64(PUBKEY).unwrap());
+
+        assert_eq!(
+            pubkey.as_eth_address_checksummed().unwrap(),
+            "0x7E5F4552091A69125d5DfCb7b8C2659029395Bdf"
+        );
+    }
+}
@@ -1,35 +1,174 @@
+use cosmwasm_std::{Binary, CanonicalAddr, ReadonlyStorage, StdError, StdResult, Storage};
 use sha2::{Digest, Sha256};
-use std::convert::TryInto;
+
+/// Argon2id cost parameters, every hash or compare pays for `t_cost` passes over `m_cost` KiB
+/// so keep them as low as the contract's gas budget requires
+#[cfg(feature = "argon2")]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Argon2Params {
+    /// Memory in KiB
+    pub m_cost: u32,
+    /// Iterations
+    pub t_cost: u32,
+    /// Lanes
+    pub p_cost: u32,
+}
 
 pub trait ViewingKey<const KEY_SIZE: usize>: ToString {
+    /// Prepended to keys made with create
+    const PREFIX: &'static str = "api_key_";
+
+    /// Derives a new key from the contract's entropy, its prng seed and the user's entropy,
+    /// the same inputs always give the same key. Each input is prefixed with its length so
+    /// bytes can't be moved from one input to the next without changing the key
+    fn create(entropy: &[u8], prng_seed: &[u8], user_entropy: &[u8]) -> String {
+        let mut hasher = Sha256::new();
+        for input in [prng_seed, entropy, user_entropy] {
+            hasher.update((input.len() as u64).to_be_bytes());
+            hasher.update(input);
+        }
+
+        format!(
+            "{}{}",
+            Self::PREFIX,
+            Binary::from(hasher.finalize().as_slice()).to_base64()
+        )
+    }
+
+    /// Constant time comparison, both hashes are walked to the longest length and
+    /// a length mismatch is folded into the result instead of returning early
     fn compare_hashes(s1: &[u8], s2: &[u8]) -> bool {
-        s1.eq(s2)
+        let mut diff = s1.len() ^ s2.len();
+        for i in 0..s1.len().max(s2.len()) {
+            let a = s1.get(i).copied().unwrap_or(0);
+            let b = s2.get(i).copied().unwrap_or(0);
+            diff |= (a ^ b) as usize;
+        }
+        diff == 0
     }
 
     fn compare(&self, hashed: &[u8]) -> bool {
         Self::compare_hashes(&self.hash(), hashed)
     }
 
+    /// Sha256 of the key, override it with hash_with to use another digest
     fn hash(&self) -> [u8; KEY_SIZE] {
-        Sha256::digest(self.to_string().as_bytes())
-            .as_slice()
-            .try_into()
-            .expect("Incorrect password length")
+        Self::hash_with::<Sha256>(self.to_string().as_bytes())
+    }
+
+    /// Hashes data into KEY_SIZE bytes, longer digests are truncated and shorter ones
+    /// are extended by hashing the previous block again
+    fn hash_with<D: Digest>(data: &[u8]) -> [u8; KEY_SIZE] {
+        let mut result = [0u8; KEY_SIZE];
+        let mut block = D::digest(data);
+
+        let mut filled = 0;
+        while filled < KEY_SIZE {
+            let len = block.len().min(KEY_SIZE - filled);
+            result[filled..filled + len].copy_from_slice(&block[..len]);
+            filled += len;
+            block = D::digest(&block);
+        }
+
+        result
+    }
+
+    /// Argon2id hash for low entropy keys, salt should be unique per user (i.e. its address)
+    /// and at least 8 bytes long
+    #[cfg(feature = "argon2")]
+    fn argon2_hash(&self, salt: &[u8], params: &Argon2Params) -> StdResult<[u8; KEY_SIZE]> {
+        let params =
+            argon2::Params::new(params.m_cost, params.t_cost, params.p_cost, Some(KEY_SIZE))
+                .map_err(|err| StdError::generic_err(err.to_string()))?;
+
+        let mut hash = [0u8; KEY_SIZE];
+        argon2::Argon2::new(argon2::Algorithm::Argon2id, argon2::Version::V0x13, params)
+            .hash_password_into(self.to_string().as_bytes(), salt, &mut hash)
+            .map_err(|err| StdError::generic_err(err.to_string()))?;
+        Ok(hash)
+    }
+
+    #[cfg(feature = "argon2")]
+    fn argon2_compare(&self, salt: &[u8], params: &Argon2Params, hashed: &[u8]) -> StdResult<bool> {
+        Ok(Self::compare_hashes(
+            &self.argon2_hash(salt, params)?,
+            hashed,
+        ))
+    }
+}
+
+pub const VIEWING_KEYS_KEY: &[u8] = b"viewing_keys";
+
+fn viewing_key_key(owner: &CanonicalAddr) -> Vec<u8> {
+    let mut key = VIEWING_KEYS_KEY.to_vec();
+    key.extend_from_slice(owner.as_slice());
+    key
+}
+
+/// Stores the hash of the owner's key, replacing any previous one
+pub fn set_key<S: Storage, K: ViewingKey<KEY_SIZE>, const KEY_SIZE: usize>(
+    storage: &mut S,
+    owner: &CanonicalAddr,
+    key: &K,
+) {
+    storage.set(&viewing_key_key(owner), &key.hash());
+}
+
+/// Stores many keys at once, every hash is computed before anything is written
+pub fn set_keys<S: Storage, K: ViewingKey<KEY_SIZE>, const KEY_SIZE: usize>(
+    storage: &mut S,
+    keys: &[(CanonicalAddr, K)],
+) {
+    let hashes: Vec<(Vec<u8>, [u8; KEY_SIZE])> = keys
+        .iter()
+        .map(|(owner, key)| (viewing_key_key(owner), key.hash()))
+        .collect();
+
+    for (storage_key, hash) in hashes {
+        storage.set(&storage_key, &hash);
+    }
+}
+
+/// Stored key hash of the owner, can be passed straight to Auth::authenticate
+pub fn load_key_hash<S: ReadonlyStorage>(storage: &S, owner: &CanonicalAddr) -> Option<Vec<u8>> {
+    storage.get(&viewing_key_key(owner))
+}
+
+/// Replaces the owner's key only if current matches the stored one, the new hash overwrites
+/// the old in a single write so a failed rotation leaves the previous key in place
+pub fn rotate_key<S: Storage, K: ViewingKey<KEY_SIZE>, const KEY_SIZE: usize>(
+    storage: &mut S,
+    owner: &CanonicalAddr,
+    current: &K,
+    new: &K,
+) -> StdResult<()> {
+    match load_key_hash(storage, owner) {
+        Some(hash) if current.compare(&hash) => {
+            set_key(storage, owner, new);
+            Ok(())
+        }
+        _ => Err(StdError::generic_err(
+            "Wrong viewing key for this address or viewing key not set",
+        )),
     }
 }
 
 #[cfg(test)]
 mod viewing_key_tests {
-    use crate::viewing_keys::ViewingKey;
+    use crate::permit::bech32_to_canonical;
+    use crate::viewing_keys::*;
+    use cosmwasm_std::testing::MockStorage;
     use serde::{Deserialize, Serialize};
+    use sha2::{Digest, Sha256};
+    use sha3::Keccak256;
 
     #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
     #[serde(rename_all = "snake_case")]
     struct Key(pub String);
 
-    impl ToString for Key {
-        fn to_string(&self) -> String {
-            self.0.clone()
+    impl std::fmt::Display for Key {
+        fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+            f.write_str(&self.0)
         }
     }
 
@@ -64,4 +203,173 @@ mod viewing_key_tests {
         assert!(!pwd.compare(&wrong_hashed));
         assert!(!Key::compare_hashes(&hashed, &wrong_hashed));
     }
+
+    struct PrefixedKey;
+
+    impl std::fmt::Display for PrefixedKey {
+        fn fmt(&self, _: &mut std::fmt::Formatter) -> std::fmt::Result {
+            Ok(())
+        }
+    }
+
+    impl ViewingKey<32> for PrefixedKey {
+        const PREFIX: &'static str = "vk_";
+    }
+
+    #[test]
+    fn key_creation() {
+        let key = Key::create(b"block entropy", b"prng seed", b"user entropy");
+
+        assert!(key.starts_with("api_key_"));
+        assert_eq!(
+            key,
+            Key::create(b"block entropy", b"prng seed", b"user entropy")
+        );
+        assert_ne!(
+            key,
+            Key::create(b"block entropy", b"prng seed", b"other entropy")
+        );
+        assert_ne!(
+            key,
+            Key::create(b"other entropy", b"prng seed", b"user entropy")
+        );
+
+        let prefixed = PrefixedKey::create(b"block entropy", b"prng seed", b"user entropy");
+        assert_eq!(prefixed, key.replacen("api_key_", "vk_", 1));
+    }
+
+    #[test]
+    fn key_creation_input_boundaries() {
+        assert_ne!(
+            Key::create(b"entropy", b"ab", b"c"),
+            Key::create(b"entropy", b"a", b"bc")
+        );
+        assert_ne!(
+            Key::create(b"ab", b"seed", b"c"),
+            Key::create(b"a", b"seed", b"bc")
+        );
+        assert_ne!(
+            Key::create(b"", b"seed", b"entropy"),
+            Key::create(b"seed", b"", b"entropy")
+        );
+    }
+
+    struct LongKey(String);
+
+    impl std::fmt::Display for LongKey {
+        fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+            f.write_str(&self.0)
+        }
+    }
+
+    impl ViewingKey<64> for LongKey {}
+
+    struct KeccakKey(String);
+
+    impl std::fmt::Display for KeccakKey {
+        fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+            f.write_str(&self.0)
+        }
+    }
+
+    impl ViewingKey<32> for KeccakKey {
+        fn hash(&self) -> [u8; 32] {
+            Self::hash_with::<Keccak256>(self.to_string().as_bytes())
+        }
+    }
+
+    #[test]
+    fn configurable_hash() {
+        let short = Key("password".to_string()).hash();
+        assert_eq!(short.as_slice(), Sha256::digest(b"password").as_slice());
+
+        let long = LongKey("password".to_string()).hash();
+        assert_eq!(long[..32], short);
+        assert_eq!(long[32..], *Sha256::digest(&short).as_slice());
+        assert!(LongKey("password".to_string()).compare(&long));
+        assert!(!LongKey("wrong_password".to_string()).compare(&long));
+
+        let keccak = KeccakKey("password".to_string()).hash();
+        assert_eq!(keccak.as_slice(), Keccak256::digest(b"password").as_slice());
+        assert!(KeccakKey("password".to_string()).compare(&keccak));
+    }
+
+    #[test]
+    fn key_rotation() {
+        let mut storage = MockStorage::new();
+        let owner = bech32_to_canonical("secret1wxyx7cgz8pjcnzt0skqwaup8043sxnd7t7u8d8");
+        let other = bech32_to_canonical("secret1w508d6qejxtdg4y5r3zarvary0c5xw7kccrnjy");
+
+        let old = Key("password".to_string());
+        let new = Key("new_password".to_string());
+
+        // Nothing to rotate yet
+        assert!(rotate_key(&mut storage, &owner, &old, &new).is_err());
+        assert_eq!(load_key_hash(&storage, &owner), None);
+
+        set_keys(
+            &mut storage,
+            &[
+                (owner.clone(), Key("password".to_string())),
+                (other.clone(), Key("other_password".to_string())),
+            ],
+        );
+        assert!(old.compare(&load_key_hash(&storage, &owner).unwrap()));
+
+        // A failed rotation keeps the previous key
+        assert!(rotate_key(&mut storage, &owner, &Key("wrong".to_string()), &new).is_err());
+        assert!(old.compare(&load_key_hash(&storage, &owner).unwrap()));
+
+        rotate_key(&mut storage, &owner, &old, &new).unwrap();
+        let hash = load_key_hash(&storage, &owner).unwrap();
+        assert!(new.compare(&hash));
+        assert!(!old.compare(&hash));
+        assert!(
+            Key("other_password".to_string()).compare(&load_key_hash(&storage, &other).unwrap())
+        );
+    }
+
+    #[test]
+    fn different_length_hashes() {
+        let hashed = Key("password".to_string()).hash();
+
+        assert!(!Key::compare_hashes(&hashed, &hashed[..31]));
+        assert!(!Key::compare_hashes(&hashed[..31], &hashed));
+        assert!(!Key::compare_hashes(&hashed, &[]));
+
+        // Zero padding must not make a shorter hash match
+        let mut padded = hashed.to_vec();
+        padded.push(0);
+        assert!(!Key::compare_hashes(&hashed, &padded));
+
+        assert!(Key::compare_hashes(&[], &[]));
+    }
+
+    #[cfg(feature = "argon2")]
+    #[test]
+    fn argon2_hashing() {
+        use crate::viewing_keys::Argon2Params;
+
+        let params = Argon2Params {
+            m_cost: 64,
+            t_cost: 2,
+            p_cost: 1,
+        };
+        let salt = b"secret1wxyx7cgz8pjcnzt0skqwaup8043sxnd7t7u8d8";
+
+        let pwd = Key("password".to_string());
+        let hashed = pwd.argon2_hash(salt, &params).unwrap();
+
+        assert_eq!(hashed, pwd.argon2_hash(salt, &params).unwrap());
+        assert_ne!(hashed, pwd.hash());
+        assert_ne!(hashed, pwd.argon2_hash(b"another salt", &params).unwrap());
+
+        assert!(pwd.argon2_compare(salt, &params, &hashed).unwrap());
+        assert!(!Key("wrong_password".to_string())
+            .argon2_compare(salt, &params, &hashed)
+            .unwrap());
+
+        // Salts shorter than 8 bytes are refused
+        assert!(pwd.argon2_hash(b"short", &params).is_err());
+    }
 }